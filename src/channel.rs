@@ -0,0 +1,288 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum)]
+pub enum Band {
+    #[value(name = "2.4")]
+    Ghz2_4,
+    /// 5 GHz, including the 4.9 GHz public safety and 5.9 GHz ITS channels
+    #[value(name = "5")]
+    Ghz5,
+    #[value(name = "6")]
+    Ghz6,
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Band::Ghz2_4 => write!(f, "2.4 GHz"),
+            Band::Ghz5 => write!(f, "5 GHz"),
+            Band::Ghz6 => write!(f, "6 GHz"),
+        }
+    }
+}
+
+/// Returns the band and channel number of a frequency in MHz.
+pub fn frequency_to_channel(freq: u16) -> Option<(Band, u8)> {
+    let channel = |start: u16| {
+        let offset = freq - start;
+        if offset.is_multiple_of(5) {
+            u8::try_from(offset / 5).ok()
+        } else {
+            None
+        }
+    };
+
+    match freq {
+        2484 => Some((Band::Ghz2_4, 14)),
+        2412..=2472 => Some((Band::Ghz2_4, channel(2407)?)),
+        // 802.11j, channels 182 to 198 of the 5 GHz numbering
+        4910..=4990 => Some((Band::Ghz5, channel(4000)?)),
+        5000..=5925 => Some((Band::Ghz5, channel(5000)?)),
+        5935 => Some((Band::Ghz6, 2)),
+        5955..=7115 => Some((Band::Ghz6, channel(5950)?)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChannelWidth {
+    Mhz20,
+    Mhz40,
+    Mhz80,
+    Mhz160,
+    Mhz80Plus80,
+    Mhz320,
+}
+
+impl fmt::Display for ChannelWidth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChannelWidth::Mhz20 => write!(f, "20 MHz"),
+            ChannelWidth::Mhz40 => write!(f, "40 MHz"),
+            ChannelWidth::Mhz80 => write!(f, "80 MHz"),
+            ChannelWidth::Mhz160 => write!(f, "160 MHz"),
+            ChannelWidth::Mhz80Plus80 => write!(f, "80+80 MHz"),
+            ChannelWidth::Mhz320 => write!(f, "320 MHz"),
+        }
+    }
+}
+
+/// Operating channel announced by an AP in its HT, VHT and HE operation elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatingChannel {
+    /// primary channel from the HT operation element, or the HE 6 GHz operation information
+    pub primary: Option<u8>,
+    pub width: ChannelWidth,
+    /// channel number at the center of the whole channel, or of its first 80 MHz segment for
+    /// 80+80 MHz
+    pub center_segment0: Option<u8>,
+    /// center of the second 80 MHz segment for 80+80 MHz
+    pub center_segment1: Option<u8>,
+}
+
+impl OperatingChannel {
+    /// The center channels, e.g. "42" or "42+106".
+    pub fn center_channels(&self) -> Option<String> {
+        let center0 = self.center_segment0?;
+        Some(match self.center_segment1 {
+            Some(center1) => format!("{}+{}", center0, center1),
+            None => center0.to_string(),
+        })
+    }
+
+    /// Width and center segments of a VHT operation element or of the VHT operation information
+    /// of an HE operation element, `None` when it defers to the HT operation element.
+    fn from_vht(data: &[u8], primary: Option<u8>) -> Option<Self> {
+        let (&width, &ccfs0, &ccfs1) = (data.first()?, data.get(1)?, data.get(2)?);
+        let (width, center0, center1) = match width {
+            // 80, 160 or 80+80 MHz, told apart by the second center frequency segment
+            1 if ccfs1 == 0 => (ChannelWidth::Mhz80, ccfs0, None),
+            1 if ccfs1.abs_diff(ccfs0) == 8 => (ChannelWidth::Mhz160, ccfs1, None),
+            1 => (ChannelWidth::Mhz80Plus80, ccfs0, Some(ccfs1)),
+            // deprecated encodings
+            2 => (ChannelWidth::Mhz160, ccfs0, None),
+            3 => (ChannelWidth::Mhz80Plus80, ccfs0, Some(ccfs1)),
+            _ => return None,
+        };
+        Some(OperatingChannel {
+            primary,
+            width,
+            center_segment0: Some(center0),
+            center_segment1: center1,
+        })
+    }
+
+    /// HE 6 GHz operation information: primary channel, control, CCFS0, CCFS1, minimum rate.
+    fn from_he_6ghz(data: &[u8]) -> Option<Self> {
+        let (&primary, &control, &ccfs0, &ccfs1) =
+            (data.first()?, data.get(1)?, data.get(2)?, data.get(3)?);
+        let (width, center0, center1) = match control & 0x03 {
+            0 => (ChannelWidth::Mhz20, ccfs0, None),
+            1 => (ChannelWidth::Mhz40, ccfs0, None),
+            2 => (ChannelWidth::Mhz80, ccfs0, None),
+            _ if ccfs1.abs_diff(ccfs0) == 8 => (ChannelWidth::Mhz160, ccfs1, None),
+            _ => (ChannelWidth::Mhz80Plus80, ccfs0, Some(ccfs1)),
+        };
+        Some(OperatingChannel {
+            primary: Some(primary),
+            width,
+            center_segment0: Some(center0),
+            center_segment1: center1,
+        })
+    }
+}
+
+/// Returns the current channel from the DS Parameter Set element of a beacon or probe response
+/// body, whose fixed parameters are `fixed_len` bytes long.
+pub fn find_ds_channel(frame_body: &[u8], fixed_len: usize) -> Option<u8> {
+    let mut offset = fixed_len; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+
+        // DS Parameter Set (tag 3)
+        if tag_number == 3 && tag_length >= 1 {
+            return Some(frame_body[offset + 2]);
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    None
+}
+
+/// Returns the operating channel of a beacon or probe response body, whose fixed parameters are
+/// `fixed_len` bytes long. The widest description wins: HE 6 GHz operation information, then
+/// VHT, then HT.
+pub fn parse_operation_elements(frame_body: &[u8], fixed_len: usize) -> Option<OperatingChannel> {
+    let mut ht: Option<OperatingChannel> = None;
+    let mut vht: Option<&[u8]> = None;
+    let mut he: Option<OperatingChannel> = None;
+    let mut offset = fixed_len; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+        let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
+
+        match tag_number {
+            // HT Operation: primary channel, then secondary channel offset and STA channel width
+            61 if tag_data.len() >= 2 => {
+                let primary = tag_data[0];
+                let center = match (tag_data[1] & 0x04 != 0, tag_data[1] & 0x03) {
+                    (true, 1) => primary.checked_add(2),
+                    (true, 3) => primary.checked_sub(2),
+                    _ => None,
+                };
+                ht = Some(OperatingChannel {
+                    primary: Some(primary),
+                    width: if center.is_some() {
+                        ChannelWidth::Mhz40
+                    } else {
+                        ChannelWidth::Mhz20
+                    },
+                    center_segment0: center.or(Some(primary)),
+                    center_segment1: None,
+                });
+            }
+            // VHT Operation
+            192 => vht = Some(tag_data),
+            // HE Operation (element ID extension 36)
+            255 if tag_data.first() == Some(&36) => he = parse_he_operation(&tag_data[1..]),
+            _ => {}
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    let primary = ht.and_then(|ht| ht.primary);
+    he.or_else(|| vht.and_then(|data| OperatingChannel::from_vht(data, primary)))
+        .or(ht)
+}
+
+/// HE operation parameters (3 bytes), BSS color (1), basic HE-MCS and NSS set (2), then the
+/// optional VHT operation information (3), max co-hosted BSSID indicator (1) and 6 GHz operation
+/// information (5).
+fn parse_he_operation(data: &[u8]) -> Option<OperatingChannel> {
+    let params = u32::from_le_bytes([*data.first()?, *data.get(1)?, *data.get(2)?, 0]);
+    let vht_present = params & (1 << 14) != 0;
+    let cohosted_present = params & (1 << 15) != 0;
+    let six_ghz_present = params & (1 << 17) != 0;
+
+    let mut offset = 6;
+    if vht_present {
+        offset += 3;
+    }
+    if cohosted_present {
+        offset += 1;
+    }
+
+    if six_ghz_present {
+        OperatingChannel::from_he_6ghz(data.get(offset..)?)
+    } else if vht_present {
+        OperatingChannel::from_vht(data.get(6..9)?, None)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests_channel {
+    use super::*;
+
+    #[test]
+    fn test_frequency_to_channel() {
+        assert_eq!(frequency_to_channel(2412), Some((Band::Ghz2_4, 1)));
+        assert_eq!(frequency_to_channel(2484), Some((Band::Ghz2_4, 14)));
+        assert_eq!(frequency_to_channel(4940), Some((Band::Ghz5, 188)));
+        assert_eq!(frequency_to_channel(5180), Some((Band::Ghz5, 36)));
+        assert_eq!(frequency_to_channel(5885), Some((Band::Ghz5, 177)));
+        assert_eq!(frequency_to_channel(5935), Some((Band::Ghz6, 2)));
+        assert_eq!(frequency_to_channel(5955), Some((Band::Ghz6, 1)));
+        assert_eq!(frequency_to_channel(7115), Some((Band::Ghz6, 233)));
+        assert_eq!(frequency_to_channel(2413), None);
+        assert_eq!(frequency_to_channel(900), None);
+    }
+
+    #[test]
+    fn test_parse_operation_elements() {
+        // HT 40 MHz, secondary channel below
+        let ht = [
+            61, 22, 40, 0x07, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let channel = parse_operation_elements(&ht, 0).unwrap();
+        assert_eq!(channel.width, ChannelWidth::Mhz40);
+        assert_eq!(channel.center_segment0, Some(38));
+
+        // VHT 160 MHz on top of it
+        let mut body = ht.to_vec();
+        body.extend_from_slice(&[192, 5, 1, 42, 50, 0, 0]);
+        let channel = parse_operation_elements(&body, 0).unwrap();
+        assert_eq!(channel.primary, Some(40));
+        assert_eq!(channel.width, ChannelWidth::Mhz160);
+        assert_eq!(channel.center_channels().as_deref(), Some("50"));
+
+        // HE with 6 GHz operation information, 80 MHz
+        let he = [255, 12, 36, 0x00, 0x00, 0x02, 0, 0, 0, 37, 0x02, 39, 0, 0];
+        let channel = parse_operation_elements(&he, 0).unwrap();
+        assert_eq!(channel.primary, Some(37));
+        assert_eq!(channel.width, ChannelWidth::Mhz80);
+        assert_eq!(channel.center_segment0, Some(39));
+    }
+
+    #[test]
+    fn test_find_ds_channel() {
+        let body = [0, 4, b'H', b'o', b'm', b'e', 1, 1, 0x82, 3, 1, 11];
+        assert_eq!(find_ds_channel(&body, 0), Some(11));
+        assert_eq!(find_ds_channel(&body[..9], 0), None);
+    }
+}
@@ -1,3 +1,8 @@
+use crate::channel::Band;
+use crate::clock::{parse_file_time_offset, parse_time_offset};
+use crate::geo::FixQuality;
+use crate::phy::PhyGeneration;
+use crate::signal::RssiCombine;
 use clap::Parser;
 
 #[derive(Parser, Debug)]
@@ -15,14 +20,30 @@ pub struct Args {
     #[arg(
         short = 'd',
         long,
-        value_name = "WORKING DIRECTORY",
-        help = "Example: `./dumps`"
+        value_name = "PATH",
+        help = "Input directory, file or glob pattern, can be repeated. Directories are searched recursively. Example: `./dumps`"
     )]
-    pub directory: Option<String>,
+    pub directory: Vec<String>,
 
     #[arg(short, long, help = "Filter interesting APs")]
     pub filter: bool,
 
+    #[arg(
+        long,
+        help = "Only keep APs on this band (2.4, 5, 6) in the filtered output, can be repeated",
+        value_name = "GHZ",
+        value_enum
+    )]
+    pub band: Vec<Band>,
+
+    #[arg(
+        long,
+        help = "Only keep APs of this PHY generation (b, a, g, n, ac, ax, be) in the filtered output, can be repeated",
+        value_name = "GENERATION",
+        value_enum
+    )]
+    pub phy: Vec<PhyGeneration>,
+
     // kml export options
     #[arg(short, long, help = "Export the map to a KML file")]
     pub kml: bool,
@@ -37,6 +58,76 @@ pub struct Args {
     #[arg(long, help = "Path to output CSV file", value_name = "FILE PATH")]
     pub csv_output: Option<String>,
 
+    // clock options
+    #[arg(
+        long,
+        help = "Seconds added to every capture timestamp to match GPS time",
+        value_name = "SECONDS",
+        value_parser = parse_time_offset,
+        allow_negative_numbers = true
+    )]
+    pub time_offset: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Time offset for a single capture file, can be repeated. Example: `dump.pcapng=-3600`",
+        value_name = "FILE=SECONDS",
+        value_parser = parse_file_time_offset,
+        allow_negative_numbers = true
+    )]
+    pub file_time_offset: Vec<(String, f64)>,
+
+    #[arg(
+        long,
+        help = "Estimate the time offset of each capture file from embedded NMEA sentences or RSSI peaks, and apply it"
+    )]
+    pub auto_time_offset: bool,
+
+    #[arg(
+        long,
+        help = "Check the CRC of frames captured with their FCS and drop those that don't match, on top of the frames the adapter flagged"
+    )]
+    pub verify_fcs: bool,
+
+    #[arg(
+        long,
+        help = "Maximum time between two GPS fixes to interpolate a position between them. Packets in longer gaps are not positioned. No limit by default",
+        value_name = "SECONDS"
+    )]
+    pub max_gap: Option<f64>,
+
+    #[arg(
+        long,
+        help = "How the receive chains of multi-antenna adapters are combined for distance estimation (combined, max, mean, primary)",
+        value_name = "RULE",
+        value_enum,
+        default_value_t = RssiCombine::Combined
+    )]
+    pub rssi_combine: RssiCombine,
+
+    // gps fix quality options
+    #[arg(
+        long,
+        help = "Ignore GPS fixes below this quality",
+        value_name = "QUALITY",
+        value_enum
+    )]
+    pub min_fix_quality: Option<FixQuality>,
+
+    #[arg(
+        long,
+        help = "Ignore GPS fixes with a higher HDOP",
+        value_name = "HDOP"
+    )]
+    pub max_hdop: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Ignore GPS fixes using fewer satellites",
+        value_name = "COUNT"
+    )]
+    pub min_satellites: Option<u32>,
+
     // hashcat options
     #[arg(long, help = "Disable hashcat password binding")]
     pub no_hashcat: bool,
@@ -52,9 +143,10 @@ pub struct Args {
 }
 
 pub static INTRO: &str = "
-HCX Map tool is a WiFi access point mapping utility that processes packet capture files (.pcapng)
-and GPS position (.nmea) logs to estimate the geographical locations of detected WiFi access
-points. It can also bind known passwords to access points using Hashcat.
+HCX Map tool is a WiFi access point mapping utility that processes packet capture files (.pcapng,
+.pcap, .cap) and GPS position logs (.nmea, gpsd .json, .gpx) to estimate the geographical
+locations of detected WiFi access points. It can also bind known passwords to access points using
+Hashcat.
 
 *Note:*
 If there is less than 3 observations for an access point, the position is estimated using a simple
@@ -0,0 +1,449 @@
+use crate::geo::{Position, datetime_to_timestamp, get_position_at, haversine_distance};
+use crate::packets::{Capture, Packet};
+use chrono::{DateTime, Days, NaiveDateTime};
+use log::{debug, info, warn};
+use nmea::{ParseResult, parse_str};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Two sightings of an AP further apart than this are counted as separate passes.
+const PASS_GAP_SECS: f64 = 60.0;
+/// APs seen on at least two passes needed to trust the RSSI peak alignment.
+const MIN_PEAK_APS: usize = 3;
+const COARSE_STEP_SECS: f64 = 5.0;
+const FINE_STEP_SECS: f64 = 0.5;
+const MAX_SEARCH_STEPS: f64 = 20000.0;
+/// Offsets smaller than this are not worth a warning.
+const SIGNIFICANT_OFFSET_SECS: f64 = 2.0;
+
+/// Parses a time offset argument in seconds, which has to be finite.
+pub fn parse_time_offset(arg: &str) -> Result<f64, String> {
+    let offset = arg
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid offset `{}`: {}", arg, e))?;
+    if !offset.is_finite() {
+        return Err(format!("invalid offset `{}`: not a finite number", arg));
+    }
+    Ok(offset)
+}
+
+/// Parses a `FILE=SECONDS` per-file time offset argument.
+pub fn parse_file_time_offset(arg: &str) -> Result<(String, f64), String> {
+    let (file, offset) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected FILE=SECONDS, got `{}`", arg))?;
+    Ok((file.to_string(), parse_time_offset(offset)?))
+}
+
+/// Extracts the GPS time of an NMEA RMC/GGA sentence embedded in a packet comment. GGA has no
+/// date, it is taken from the packet timestamp, picking the closest day.
+pub fn parse_embedded_nmea(text: &str, packet_time: Duration) -> Option<f64> {
+    let start = text.find('$')?;
+    let sentence = text[start..].lines().next()?.trim();
+
+    let (date, time) = match parse_str(sentence).ok()? {
+        ParseResult::RMC(rmc) => (rmc.fix_date, rmc.fix_time?),
+        ParseResult::GGA(gga) => (None, gga.fix_time?),
+        _ => return None,
+    };
+
+    let datetime = match date {
+        Some(date) => NaiveDateTime::new(date, time),
+        None => {
+            let packet_date = DateTime::from_timestamp(packet_time.as_secs() as i64, 0)?
+                .naive_utc()
+                .date();
+            let packet_secs = packet_time.as_secs_f64();
+            [
+                packet_date.checked_sub_days(Days::new(1)),
+                Some(packet_date),
+                packet_date.checked_add_days(Days::new(1)),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|date| NaiveDateTime::new(date, time))
+            .min_by(|a, b| {
+                let a = (datetime_to_timestamp(a) - packet_secs).abs();
+                let b = (datetime_to_timestamp(b) - packet_secs).abs();
+                a.total_cmp(&b)
+            })?
+        }
+    };
+
+    Some(datetime_to_timestamp(&datetime))
+}
+
+/// Shifts a capture timestamp by a signed number of seconds, clamping at the epoch.
+pub fn shift_timestamp(timestamp: Duration, offset: f64) -> Duration {
+    if offset >= 0.0 {
+        timestamp + Duration::from_secs_f64(offset)
+    } else {
+        timestamp.saturating_sub(Duration::from_secs_f64(-offset))
+    }
+}
+
+/// Picks the time offset of every capture and applies it to its packets. A per-file offset
+/// wins over an estimated one (`auto`), which wins over the global offset.
+pub fn apply_time_offsets(
+    captures: &mut [Capture],
+    positions: &[Position],
+    max_gap: f64,
+    global_offset: f64,
+    file_offsets: &[(String, f64)],
+    auto: bool,
+) {
+    for capture in captures.iter_mut() {
+        let file_offset = file_offsets
+            .iter()
+            .rev()
+            .find(|(file, _)| capture.path.ends_with(Path::new(file)))
+            .map(|(_, offset)| *offset);
+
+        // estimating is only worth it when asked to, or when the capture looks misaligned
+        let estimate =
+            if auto || file_offset.is_none() && needs_estimate(capture, positions, global_offset) {
+                estimate_offset(capture, positions, max_gap)
+            } else {
+                None
+            };
+
+        let (offset, source) = match (file_offset, estimate) {
+            (Some(offset), _) => (offset, "per-file"),
+            (None, Some((offset, method))) if auto => (offset, method),
+            _ => (global_offset, "global"),
+        };
+
+        if !auto
+            && let Some((estimated, method)) = estimate
+            && (estimated - offset).abs() > SIGNIFICANT_OFFSET_SECS
+        {
+            warn!(
+                "{:?} seems {:+.1} s off GPS time ({}), consider --auto-time-offset or --file-time-offset",
+                capture.path,
+                estimated - offset,
+                method
+            );
+        }
+
+        if offset != 0.0 {
+            info!(
+                "Applied {} time offset of {:+.1} s to {:?}",
+                source, offset, capture.path
+            );
+            for packet in capture.packets.iter_mut() {
+                packet.timestamp = shift_timestamp(packet.timestamp, offset);
+            }
+        }
+    }
+}
+
+/// True when less than half of the packets, shifted by `offset`, fall within the GPS track.
+fn needs_estimate(capture: &Capture, positions: &[Position], offset: f64) -> bool {
+    if !capture.gps_samples.is_empty() {
+        return true;
+    }
+    let (Some(first), Some(last)) = (positions.first(), positions.last()) else {
+        return false;
+    };
+
+    let inside = capture
+        .packets
+        .iter()
+        .filter(|p| {
+            let t = shift_timestamp(p.timestamp, offset).as_secs_f64();
+            t >= first.timestamp && t <= last.timestamp
+        })
+        .count();
+    inside * 2 < capture.packets.len()
+}
+
+fn estimate_offset(
+    capture: &Capture,
+    positions: &[Position],
+    max_gap: f64,
+) -> Option<(f64, &'static str)> {
+    if let Some(offset) = estimate_from_gps_samples(&capture.gps_samples) {
+        debug!(
+            "Estimated {:+.1} s for {:?} from {} embedded NMEA sentences",
+            offset,
+            capture.path,
+            capture.gps_samples.len()
+        );
+        return Some((offset, "embedded NMEA"));
+    }
+
+    let offset = estimate_from_rssi_peaks(&capture.packets, positions, max_gap)?;
+    debug!(
+        "Estimated {:+.1} s for {:?} from RSSI peak alignment",
+        offset, capture.path
+    );
+    Some((offset, "RSSI peak alignment"))
+}
+
+/// Median difference between the GPS time and the capture time of the embedded NMEA sentences.
+fn estimate_from_gps_samples(samples: &[(Duration, f64)]) -> Option<f64> {
+    let mut offsets: Vec<f64> = samples
+        .iter()
+        .map(|(packet_time, gps_time)| gps_time - packet_time.as_secs_f64())
+        .collect();
+    if offsets.is_empty() {
+        return None;
+    }
+
+    offsets.sort_by(f64::total_cmp);
+    Some(offsets[offsets.len() / 2])
+}
+
+/// Looks for the offset that makes the RSSI peaks of each AP, one per pass, land at the same
+/// place. Needs APs seen on several passes, a single pass can't tell a time shift from the AP
+/// being further along the road.
+fn estimate_from_rssi_peaks(
+    packets: &[Packet],
+    positions: &[Position],
+    max_gap: f64,
+) -> Option<f64> {
+    let (first, last) = (positions.first()?, positions.last()?);
+
+    let mut signals: HashMap<[u8; 6], Vec<(f64, i8)>> = HashMap::new();
+    for packet in packets {
+        if let (Some(mac), Some(signal)) = (packet.source_address, packet.signal_strength) {
+            signals
+                .entry(mac)
+                .or_default()
+                .push((packet.timestamp.as_secs_f64(), signal));
+        }
+    }
+
+    let mut peaks: Vec<Vec<f64>> = Vec::new();
+    for samples in signals.values_mut() {
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut ap_peaks = Vec::new();
+        let mut best: Option<(f64, i8)> = None;
+        let mut last_time = f64::NEG_INFINITY;
+        for &(time, signal) in samples.iter() {
+            if time - last_time > PASS_GAP_SECS
+                && let Some((peak_time, _)) = best.take()
+            {
+                ap_peaks.push(peak_time);
+            }
+            if best.is_none_or(|(_, best_signal)| signal > best_signal) {
+                best = Some((time, signal));
+            }
+            last_time = time;
+        }
+        if let Some((peak_time, _)) = best {
+            ap_peaks.push(peak_time);
+        }
+
+        if ap_peaks.len() >= 2 {
+            peaks.push(ap_peaks);
+        }
+    }
+
+    if peaks.len() < MIN_PEAK_APS {
+        return None;
+    }
+
+    // only offsets that put every peak inside the GPS track
+    let peak_start = peaks
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    let peak_end = peaks
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let min_offset = first.timestamp - peak_start;
+    let max_offset = last.timestamp - peak_end;
+    if min_offset > max_offset {
+        return None;
+    }
+
+    let coarse_step = ((max_offset - min_offset) / MAX_SEARCH_STEPS).max(COARSE_STEP_SECS);
+    let (coarse, coarse_spread, median_spread) = search_offset(
+        &peaks,
+        positions,
+        max_gap,
+        min_offset,
+        max_offset,
+        coarse_step,
+    )?;
+
+    // a flat cost (parked car, straight road) means the peaks don't constrain the offset
+    if coarse_spread > median_spread * 0.5 {
+        return None;
+    }
+
+    let (fine, _, _) = search_offset(
+        &peaks,
+        positions,
+        max_gap,
+        (coarse - coarse_step).max(min_offset),
+        (coarse + coarse_step).min(max_offset),
+        FINE_STEP_SECS,
+    )?;
+    Some(fine)
+}
+
+/// Returns the offset with the smallest peak spread, that spread, and the median spread.
+fn search_offset(
+    peaks: &[Vec<f64>],
+    positions: &[Position],
+    max_gap: f64,
+    from: f64,
+    to: f64,
+    step: f64,
+) -> Option<(f64, f64, f64)> {
+    let mut spreads: Vec<(f64, f64)> = Vec::new();
+    let mut offset = from;
+    while offset <= to {
+        if let Some(spread) = peak_spread(peaks, positions, max_gap, offset) {
+            spreads.push((offset, spread));
+        }
+        offset += step;
+    }
+
+    let (best_offset, best_spread) = spreads.iter().copied().min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let mut sorted: Vec<f64> = spreads.iter().map(|(_, spread)| *spread).collect();
+    sorted.sort_by(f64::total_cmp);
+    Some((best_offset, best_spread, sorted[sorted.len() / 2]))
+}
+
+/// Mean distance, in meters, between each pass peak and the centroid of its AP's peaks.
+fn peak_spread(
+    peaks: &[Vec<f64>],
+    positions: &[Position],
+    max_gap: f64,
+    offset: f64,
+) -> Option<f64> {
+    let mut total = 0.0;
+    let mut count = 0;
+
+    for ap_peaks in peaks {
+        let located: Option<Vec<Position>> = ap_peaks
+            .iter()
+            .map(|time| {
+                get_position_at(&Duration::from_secs_f64(time + offset), positions, max_gap).ok()
+            })
+            .collect();
+        let Some(located) = located else {
+            continue;
+        };
+
+        let lat = located.iter().map(|p| p.latitude).sum::<f64>() / located.len() as f64;
+        let lon = located.iter().map(|p| p.longitude).sum::<f64>() / located.len() as f64;
+        total += located
+            .iter()
+            .map(|p| haversine_distance(p.latitude, p.longitude, lat, lon))
+            .sum::<f64>()
+            / located.len() as f64;
+        count += 1;
+    }
+
+    if count < MIN_PEAK_APS {
+        return None;
+    }
+    Some(total / count as f64)
+}
+
+#[cfg(test)]
+mod tests_clock {
+    use super::*;
+
+    #[test]
+    fn test_parse_embedded_nmea() {
+        let packet_time = Duration::from_secs(1_790_856_000 - 3600);
+
+        let rmc = "GPS: $GPRMC,120000.50,A,4851.0000,N,00221.0000,E,10.0,90.0,011026,,,A*5D";
+        assert_eq!(parse_embedded_nmea(rmc, packet_time), Some(1_790_856_000.5));
+
+        // GGA has no date, 00:00:10 belongs to the day after a packet at 23:59:50
+        let gga = "$GPGGA,000010.00,4851.0000,N,00221.0000,E,1,08,0.9,35.0,M,47.0,M,,*50";
+        let packet_time = Duration::from_secs(1_790_899_190);
+        assert_eq!(parse_embedded_nmea(gga, packet_time), Some(1_790_899_210.0));
+
+        assert_eq!(parse_embedded_nmea("no gps", packet_time), None);
+    }
+
+    #[test]
+    fn test_estimate_from_gps_samples() {
+        // one sentence embedded late by the capture tool
+        let samples = [
+            (Duration::from_secs(100), 103.0),
+            (Duration::from_secs(200), 203.0),
+            (Duration::from_secs(300), 350.0),
+        ];
+        assert_eq!(estimate_from_gps_samples(&samples), Some(3.0));
+        assert_eq!(estimate_from_gps_samples(&[]), None);
+    }
+
+    #[test]
+    fn test_estimate_from_rssi_peaks() {
+        const START: f64 = 1_790_856_000.0;
+        const OFFSET: f64 = 42.0;
+        const METERS_PER_DEGREE: f64 = 111_318.0;
+
+        // out and back along the equator at 10 m/s, 2 km each way
+        let x_at = |t: f64| {
+            if t < 200.0 {
+                t * 10.0
+            } else {
+                (400.0 - t) * 10.0
+            }
+        };
+        let positions: Vec<Position> = (0..=400)
+            .map(|t| Position {
+                latitude: 0.0,
+                longitude: x_at(t as f64) / METERS_PER_DEGREE,
+                timestamp: START + t as f64,
+                ..Default::default()
+            })
+            .collect();
+
+        // APs heard within 200 m, by a capture clock running OFFSET seconds behind GPS time
+        let mut packets = Vec::new();
+        for (i, ap_x) in [300.0, 800.0, 1300.0].into_iter().enumerate() {
+            for t in 0..=400 {
+                let distance = (x_at(t as f64) - ap_x).abs();
+                if distance < 200.0 {
+                    packets.push(Packet {
+                        timestamp: Duration::from_secs_f64(START + t as f64 - OFFSET),
+                        source_address: Some([0, 0, 0, 0, 0, i as u8]),
+                        signal_strength: Some((-30.0 - distance / 10.0) as i8),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        let estimated = estimate_from_rssi_peaks(&packets, &positions, f64::INFINITY).unwrap();
+        assert!((estimated - OFFSET).abs() <= 1.0, "estimated {}", estimated);
+
+        let capture = Capture {
+            path: "a.pcapng".into(),
+            packets,
+            gps_samples: Vec::new(),
+            bad_fcs_frames: 0,
+        };
+        assert!(!needs_estimate(&capture, &positions, OFFSET));
+        assert!(needs_estimate(&capture, &positions, -600.0));
+    }
+
+    #[test]
+    fn test_parse_file_time_offset() {
+        assert_eq!(
+            parse_file_time_offset("dumps/a.pcapng=-3600.5"),
+            Ok(("dumps/a.pcapng".to_string(), -3600.5))
+        );
+        assert!(parse_file_time_offset("a.pcapng").is_err());
+        assert!(parse_file_time_offset("a.pcapng=nan").is_err());
+        assert!(parse_time_offset("inf").is_err());
+        assert_eq!(parse_time_offset("-1.5"), Ok(-1.5));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::geo::Position;
+use crate::security::Akm;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// LLC/SNAP header of an EAPOL payload: AA AA 03, OUI 00 00 00, ethertype 0x888E
+const EAPOL_LLC_SNAP: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x88, 0x8e];
+/// PMKID key data encapsulation: OUI 00:0f:ac, data type 4
+const PMKID_KDE: [u8; 4] = [0x00, 0x0f, 0xac, 0x04];
+
+const KEY_INFO_PAIRWISE: u16 = 0x0008;
+const KEY_INFO_ACK: u16 = 0x0080;
+const KEY_INFO_MIC: u16 = 0x0100;
+const KEY_INFO_SECURE: u16 = 0x0200;
+
+/// Message of the 4-way handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EapolMessage {
+    /// AP nonce, may carry the PMKID
+    M1,
+    /// station nonce and MIC
+    M2,
+    /// group key and MIC
+    M3,
+    /// station acknowledgement
+    M4,
+}
+
+impl fmt::Display for EapolMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EapolMessage::M1 => write!(f, "M1"),
+            EapolMessage::M2 => write!(f, "M2"),
+            EapolMessage::M3 => write!(f, "M3"),
+            EapolMessage::M4 => write!(f, "M4"),
+        }
+    }
+}
+
+/// Pairwise EAPOL-Key frame of a 4-way handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EapolKey {
+    pub message: EapolMessage,
+    /// key descriptor of an M1, kept to look for a PMKID once the MIC length is known
+    descriptor: Vec<u8>,
+}
+
+impl EapolKey {
+    /// M1 carrying a non-zero PMKID in its key data. The key data follows the MIC, whose length
+    /// depends on the AKM.
+    pub fn has_pmkid(&self, mic_len: usize) -> bool {
+        let offset = 77 + mic_len;
+        let Some(length) = self.descriptor.get(offset..offset + 2) else {
+            return false;
+        };
+        let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+        self.descriptor
+            .get(offset + 2..offset + 2 + length)
+            .is_some_and(has_pmkid)
+    }
+}
+
+/// MIC length of the EAPOL-Key frames of an AP: 24 bytes for the SHA-384 based AKMs, 16 for the
+/// others.
+pub fn mic_len(akms: &[Akm]) -> usize {
+    let sha384 = akms.iter().any(|akm| {
+        matches!(
+            akm,
+            Akm::SuiteB192
+                | Akm::FtIeee8021xSha384
+                | Akm::FtPskSha384
+                | Akm::PskSha384
+                | Akm::SaeExtKey
+                | Akm::FtSaeExtKey
+        )
+    });
+    if sha384 { 24 } else { 16 }
+}
+
+/// When and where a handshake message was first captured.
+#[derive(Debug, Clone)]
+pub struct HandshakeCapture {
+    pub timestamp: Duration,
+    pub position: Option<Position>,
+}
+
+/// Handshake messages and PMKID captured for an AP, across all its clients.
+#[derive(Debug, Clone, Default)]
+pub struct Handshake {
+    pub messages: BTreeMap<EapolMessage, HandshakeCapture>,
+    pub pmkid: Option<HandshakeCapture>,
+}
+
+impl Handshake {
+    /// Records the first capture of `key`, sent with MICs of `mic_len` bytes.
+    pub fn add(
+        &mut self,
+        key: &EapolKey,
+        mic_len: usize,
+        timestamp: Duration,
+        position: Option<Position>,
+    ) {
+        let capture = HandshakeCapture {
+            timestamp,
+            position,
+        };
+        if self.pmkid.is_none() && key.has_pmkid(mic_len) {
+            self.pmkid = Some(capture.clone());
+        }
+        self.messages.entry(key.message).or_insert(capture);
+    }
+
+    /// Enough was captured to attack the passphrase offline: a PMKID, or the M2 MIC with the AP
+    /// nonce of M1 or M3.
+    pub fn is_crackable(&self) -> bool {
+        let has = |message| self.messages.contains_key(&message);
+        self.pmkid.is_some()
+            || (has(EapolMessage::M2) && (has(EapolMessage::M1) || has(EapolMessage::M3)))
+    }
+
+    /// e.g. "M1+M2+M3".
+    pub fn messages_string(&self) -> String {
+        let messages: Vec<String> = self.messages.keys().map(|m| m.to_string()).collect();
+        messages.join("+")
+    }
+
+    /// The earliest capture of a message or PMKID.
+    pub fn first_capture(&self) -> Option<&HandshakeCapture> {
+        self.messages
+            .values()
+            .chain(self.pmkid.iter())
+            .min_by_key(|capture| capture.timestamp)
+    }
+}
+
+/// Returns the EAPOL-Key frame of a data frame payload, following the 802.11 header. Group key
+/// handshakes and other EAPOL packets are ignored.
+pub fn parse_eapol_key(payload: &[u8]) -> Option<EapolKey> {
+    let eapol = payload.strip_prefix(&EAPOL_LLC_SNAP)?;
+
+    // EAPOL header: version, packet type (3 = key), body length
+    if eapol.len() < 4 || eapol[1] != 3 {
+        return None;
+    }
+    let length = u16::from_be_bytes([eapol[2], eapol[3]]) as usize;
+    let key = eapol.get(4..4 + length)?;
+
+    // descriptor type (2 = RSN, 254 = WPA), key info, key length, replay counter (8), nonce (32),
+    // IV (16), RSC (8), reserved (8), MIC (16 or 24), key data length, key data
+    if key.len() < 95 || !matches!(key[0], 2 | 254) {
+        return None;
+    }
+    let key_info = u16::from_be_bytes([key[1], key[2]]);
+    if key_info & KEY_INFO_PAIRWISE == 0 {
+        return None;
+    }
+    let nonce = &key[13..45];
+
+    let message = match (key_info & KEY_INFO_ACK != 0, key_info & KEY_INFO_MIC != 0) {
+        (true, false) => EapolMessage::M1,
+        (true, true) => EapolMessage::M3,
+        // M4 is secured on WPA2, and has a zero nonce on most WPA stacks
+        (false, true) if key_info & KEY_INFO_SECURE != 0 || nonce.iter().all(|&b| b == 0) => {
+            EapolMessage::M4
+        }
+        (false, true) => EapolMessage::M2,
+        (false, false) => return None,
+    };
+
+    let descriptor = if message == EapolMessage::M1 {
+        key.to_vec()
+    } else {
+        Vec::new()
+    };
+    Some(EapolKey {
+        message,
+        descriptor,
+    })
+}
+
+/// Looks for a non-zero PMKID KDE in the key data of an M1. The key data is a list of vendor
+/// specific elements: type 0xdd, length, OUI and data type, data.
+fn has_pmkid(key_data: &[u8]) -> bool {
+    let mut offset = 0;
+
+    while offset + 2 <= key_data.len() {
+        let kde_type = key_data[offset];
+        let kde_length = key_data[offset + 1] as usize;
+
+        let Some(kde) = key_data.get(offset + 2..offset + 2 + kde_length) else {
+            break;
+        };
+        if kde_type == 0xdd
+            && let Some(pmkid) = kde.strip_prefix(&PMKID_KDE)
+            && pmkid.len() >= 16
+        {
+            return pmkid[..16].iter().any(|&b| b != 0);
+        }
+
+        offset += 2 + kde_length;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests_eapol {
+    use super::*;
+
+    fn eapol_key(key_info: u16, nonce: u8, mic_len: usize, key_data: &[u8]) -> Vec<u8> {
+        let mut key = vec![2];
+        key.extend_from_slice(&key_info.to_be_bytes());
+        key.extend_from_slice(&[0; 10]); // key length, replay counter
+        key.extend_from_slice(&[nonce; 32]);
+        key.extend_from_slice(&[0; 32]); // IV, RSC, reserved
+        key.extend(std::iter::repeat_n(0, mic_len));
+        key.extend_from_slice(&(key_data.len() as u16).to_be_bytes());
+        key.extend_from_slice(key_data);
+
+        let mut payload = EAPOL_LLC_SNAP.to_vec();
+        payload.extend_from_slice(&[2, 3]);
+        payload.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        payload.extend(key);
+        payload
+    }
+
+    #[test]
+    fn test_parse_eapol_key() {
+        let mut pmkid_kde = vec![0xdd, 20];
+        pmkid_kde.extend_from_slice(&PMKID_KDE);
+        pmkid_kde.extend_from_slice(&[0x5a; 16]);
+
+        let m1 = parse_eapol_key(&eapol_key(0x008a, 0x11, 16, &pmkid_kde)).unwrap();
+        assert_eq!(m1.message, EapolMessage::M1);
+        assert!(m1.has_pmkid(16));
+        let m2 = parse_eapol_key(&eapol_key(0x010a, 0x22, 16, &[])).unwrap();
+        assert_eq!(m2.message, EapolMessage::M2);
+        let m3 = parse_eapol_key(&eapol_key(0x13ca, 0x11, 16, &[])).unwrap();
+        assert_eq!(m3.message, EapolMessage::M3);
+        let m4 = parse_eapol_key(&eapol_key(0x030a, 0x00, 16, &[])).unwrap();
+        assert_eq!(m4.message, EapolMessage::M4);
+
+        // group key handshake
+        assert_eq!(parse_eapol_key(&eapol_key(0x0382, 0x00, 16, &[])), None);
+
+        // SAE-EXT-KEY M1 with a 24 byte MIC
+        let m1 = parse_eapol_key(&eapol_key(0x0088, 0x11, 24, &pmkid_kde)).unwrap();
+        assert_eq!(mic_len(&[Akm::SaeExtKey]), 24);
+        assert!(m1.has_pmkid(24));
+        assert!(!m1.has_pmkid(mic_len(&[Akm::Psk])));
+
+        let mut handshake = Handshake::default();
+        handshake.add(&m2, 16, Duration::from_secs(2), None);
+        assert!(!handshake.is_crackable());
+        handshake.add(&m3, 16, Duration::from_secs(3), None);
+        assert!(handshake.is_crackable());
+        assert_eq!(handshake.messages_string(), "M2+M3");
+        assert_eq!(
+            handshake.first_capture().map(|c| c.timestamp),
+            Some(Duration::from_secs(2))
+        );
+    }
+}
@@ -1,28 +1,160 @@
-use crate::WORKING_DIR;
-use chrono::Datelike;
-use chrono::{NaiveDate, NaiveDateTime};
-use log::trace;
-use nmea::Nmea;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::gpsd::{GpsdState, parse_gpsd_line};
+use crate::gpx::parse_gpx;
+use crate::input::{InputKind, input_files, open_input};
+use crate::nmea_log::{NmeaState, parse_nmea_line};
+use crate::signal::ChainSignal;
+use chrono::NaiveDateTime;
+use log::{debug, error, info, trace, warn};
+use nmea::sentences::FixType;
+use std::io::{BufRead, Read};
+use std::sync::Arc;
 use std::time::Duration;
 
 const RSSI_AT_1M: f64 = -35.0;
 const PATH_LOSS_EXPONENT: f64 = 2.5;
 const MIN_DISTANCE_BETWEEN_OBS: f64 = 5.0; // meters
+/// assumed user equivalent range error in meters, turns an HDOP into a horizontal error
+const UERE: f64 = 5.0;
+/// horizontal error, in meters, up to which a fix gets the full observation weight
+const NOMINAL_HORIZONTAL_ERROR: f64 = 5.0;
+
+/// GPS fix quality, from worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, clap::ValueEnum)]
+pub enum FixQuality {
+    /// no valid fix
+    Invalid,
+    /// dead reckoning, manual or simulated position
+    Estimated,
+    /// autonomous GNSS fix
+    Gps,
+    /// differential (DGPS, SBAS) or PPS fix
+    Dgps,
+    /// RTK fixed or float solution
+    Rtk,
+}
 
-#[derive(Debug, Clone)]
+impl From<FixType> for FixQuality {
+    fn from(fix_type: FixType) -> Self {
+        match fix_type {
+            FixType::Invalid => FixQuality::Invalid,
+            FixType::Estimated | FixType::Manual | FixType::Simulation => FixQuality::Estimated,
+            FixType::Gps => FixQuality::Gps,
+            FixType::DGps | FixType::Pps => FixQuality::Dgps,
+            FixType::Rtk | FixType::FloatRtk => FixQuality::Rtk,
+        }
+    }
+}
+
+/// Thresholds under which a GPS fix is too poor to be used. Fixes that don't report a value are
+/// kept, invalid fixes never are.
+#[derive(Debug, Clone, Default)]
+pub struct FixFilter {
+    pub min_quality: Option<FixQuality>,
+    pub max_hdop: Option<f64>,
+    pub min_satellites: Option<u32>,
+}
+
+impl FixFilter {
+    pub fn accepts(&self, position: &Position) -> bool {
+        if position.fix_quality == Some(FixQuality::Invalid) {
+            return false;
+        }
+        if let (Some(min_quality), Some(quality)) = (self.min_quality, position.fix_quality)
+            && quality < min_quality
+        {
+            return false;
+        }
+        if let (Some(max_hdop), Some(hdop)) = (self.max_hdop, position.hdop)
+            && hdop > max_hdop
+        {
+            return false;
+        }
+        if let (Some(min_satellites), Some(satellites)) = (self.min_satellites, position.satellites)
+            && satellites < min_satellites
+        {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Position {
     pub latitude: f64,
     pub longitude: f64,
-    pub timestamp: i64,
+    /// seconds since the epoch, with sub-second precision
+    pub timestamp: f64,
+    /// meters above mean sea level
+    pub altitude: Option<f64>,
+    /// ground speed in m/s
+    pub speed: Option<f64>,
+    /// course over ground in degrees from true north
+    pub track: Option<f64>,
+    /// estimated horizontal error in meters (gpsd `eph`)
+    pub eph: Option<f64>,
+    /// estimated longitude error in meters (gpsd `epx`)
+    pub epx: Option<f64>,
+    /// estimated latitude error in meters (gpsd `epy`)
+    pub epy: Option<f64>,
+    /// horizontal dilution of precision
+    pub hdop: Option<f64>,
+    /// satellites used in the fix
+    pub satellites: Option<u32>,
+    pub fix_quality: Option<FixQuality>,
+}
+
+impl Position {
+    /// Estimated horizontal error in meters, from gpsd's error estimates or else the HDOP.
+    pub fn horizontal_error(&self) -> Option<f64> {
+        self.eph
+            .or_else(|| Some(self.epx?.hypot(self.epy?)))
+            .or_else(|| self.hdop.map(|hdop| hdop * UERE))
+    }
+
+    /// Observation weight factor, 1.0 for a good (or unknown) fix and shrinking with the inverse
+    /// variance of the horizontal error, so cold-start fixes barely count.
+    pub fn uncertainty_weight(&self) -> f64 {
+        match self.horizontal_error() {
+            Some(error) => (NOMINAL_HORIZONTAL_ERROR / error.max(NOMINAL_HORIZONTAL_ERROR)).powi(2),
+            None => 1.0,
+        }
+    }
+}
+
+/// Why no position could be found for a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionMiss {
+    /// there is no GPS fix at all
+    NoFix,
+    /// the surrounding fixes are further apart than the maximum gap
+    Gap,
+    /// the timestamp is before the first or after the last fix
+    OutOfRange,
 }
 
 #[derive(Debug, Clone)]
 pub struct Observation {
     pub position: Position,
+    /// signal the distance is estimated from, the chains combined according to `--rssi-combine`
     pub signal_strength: i8,
     pub distance: f64,
+    /// Capture interface the observation was heard on
+    pub interface: Arc<str>,
+    /// per-chain signals, empty unless the adapter reports more than one receive chain
+    pub chains: Vec<ChainSignal>,
+    /// noise floor in dBm
+    pub noise: Option<i8>,
+    /// heard while the adapter was tuned to another channel than the one the AP advertises,
+    /// usually adjacent-channel leakage
+    pub off_channel: bool,
+}
+
+impl Observation {
+    /// Signal to noise ratio in dB, when the adapter reports the noise floor.
+    pub fn snr(&self) -> Option<i16> {
+        self.noise
+            .map(|noise| self.signal_strength as i16 - noise as i16)
+    }
 }
 
 pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
@@ -53,7 +185,8 @@ pub fn weighted_centroid(observations: &[Observation]) -> Option<Position> {
 
     for obs in observations {
         // linear mapping: -100 dBm --> 0, -25 dBm --> 100
-        let weight = ((obs.signal_strength as f64 + 100.0) / 75.0 * 100.0).max(0.0);
+        let weight = ((obs.signal_strength as f64 + 100.0) / 75.0 * 100.0).max(0.0)
+            * obs.position.uncertainty_weight();
         total_weight += weight;
         weighted_lat += obs.position.latitude * weight;
         weighted_lon += obs.position.longitude * weight;
@@ -75,6 +208,7 @@ pub fn weighted_centroid(observations: &[Observation]) -> Option<Position> {
             latitude: lat,
             longitude: lon,
             timestamp: observations[0].position.timestamp,
+            ..Default::default()
         });
     }
 
@@ -82,6 +216,7 @@ pub fn weighted_centroid(observations: &[Observation]) -> Option<Position> {
         latitude: weighted_lat / total_weight,
         longitude: weighted_lon / total_weight,
         timestamp: observations[0].position.timestamp,
+        ..Default::default()
     })
 }
 
@@ -124,7 +259,7 @@ pub fn trilateration(observations: &[Observation]) -> Option<Position> {
             // higher weight for stronger signals (less negative RSSI)
             // -100 dBm --> 0.0, -30 dBm --> 1.0
             let weight = ((obs.signal_strength as f64 + 100.0) / 70.0).clamp(0.0, 1.0);
-            let weight_sq = weight * weight; // Square for more emphasis
+            let weight_sq = weight * weight * obs.position.uncertainty_weight(); // Square for more emphasis
 
             grad_lat += weight_sq * error * dy / calculated_distance / 110540.0;
             grad_lon += weight_sq * error * dx / calculated_distance / (111320.0 * cos_lat);
@@ -155,42 +290,71 @@ pub fn trilateration(observations: &[Observation]) -> Option<Position> {
         latitude: est_lat,
         longitude: est_lon,
         timestamp: observations.first()?.position.timestamp,
+        ..Default::default()
     })
 }
 
-pub fn get_position_at(timestamp: &Duration, positions: &[Position]) -> Option<Position> {
-    let timestamp_secs = timestamp.as_secs() as i64;
+/// Converts a UTC date and time to seconds since the epoch, keeping the fractional seconds.
+pub fn datetime_to_timestamp(datetime: &NaiveDateTime) -> f64 {
+    datetime.and_utc().timestamp_micros() as f64 / 1e6
+}
+
+/// Interpolates the position at `timestamp` between the two surrounding fixes. Fixes more than
+/// `max_gap` seconds apart (GPS dropout, receiver unplugged) are not interpolated.
+pub fn get_position_at(
+    timestamp: &Duration,
+    positions: &[Position],
+    max_gap: f64,
+) -> Result<Position, PositionMiss> {
+    let timestamp_secs = timestamp.as_secs_f64();
 
     if positions.is_empty() {
-        return None;
+        return Err(PositionMiss::NoFix);
     }
 
     if positions.len() == 1 {
-        return Some(positions[0].clone());
+        return Ok(positions[0].clone());
     }
 
-    for i in 0..positions.len() - 1 {
-        let pos1 = &positions[i];
-        let pos2 = &positions[i + 1];
-
-        if timestamp_secs >= pos1.timestamp && timestamp_secs <= pos2.timestamp {
-            let ratio = if pos2.timestamp != pos1.timestamp {
-                (timestamp_secs - pos1.timestamp) as f64 / (pos2.timestamp - pos1.timestamp) as f64
-            } else {
-                0.0
-            };
+    // positions are sorted, find the first fix at or after the timestamp
+    let next = positions.partition_point(|p| p.timestamp < timestamp_secs);
+    if next == positions.len() {
+        return Err(PositionMiss::OutOfRange);
+    }
+    let i = next.saturating_sub(1);
+    let pos1 = &positions[i];
+    let pos2 = &positions[i + 1];
 
-            let latitude = pos1.latitude + (pos2.latitude - pos1.latitude) * ratio;
-            let longitude = pos1.longitude + (pos2.longitude - pos1.longitude) * ratio;
+    if timestamp_secs < pos1.timestamp || timestamp_secs > pos2.timestamp {
+        return Err(PositionMiss::OutOfRange);
+    }
 
-            return Some(Position {
-                latitude,
-                longitude,
-                timestamp: timestamp_secs,
-            });
-        }
+    if pos2.timestamp - pos1.timestamp > max_gap {
+        return Err(PositionMiss::Gap);
     }
-    None
+
+    let ratio = if pos2.timestamp != pos1.timestamp {
+        (timestamp_secs - pos1.timestamp) / (pos2.timestamp - pos1.timestamp)
+    } else {
+        0.0
+    };
+
+    let latitude = pos1.latitude + (pos2.latitude - pos1.latitude) * ratio;
+    let longitude = pos1.longitude + (pos2.longitude - pos1.longitude) * ratio;
+    let altitude = match (pos1.altitude, pos2.altitude) {
+        (Some(alt1), Some(alt2)) => Some(alt1 + (alt2 - alt1) * ratio),
+        (alt1, alt2) => alt1.or(alt2),
+    };
+    // the remaining fix attributes are taken from the closest fix
+    let nearest = if ratio < 0.5 { pos1 } else { pos2 };
+
+    Ok(Position {
+        latitude,
+        longitude,
+        timestamp: timestamp_secs,
+        altitude,
+        ..nearest.clone()
+    })
 }
 
 pub fn rssi_to_distance(rssi: i8) -> f64 {
@@ -208,7 +372,7 @@ pub fn filter_close_observations(observations: &mut Vec<Observation>) {
         return;
     }
 
-    observations.sort_by(|a, b| b.signal_strength.cmp(&a.signal_strength));
+    observations.sort_by_key(|o| std::cmp::Reverse(o.signal_strength));
 
     let mut filtered = Vec::new();
     filtered.push(observations[0].clone());
@@ -235,63 +399,217 @@ pub fn filter_close_observations(observations: &mut Vec<Observation>) {
     *observations = filtered;
 }
 
-pub fn get_positions() -> Vec<Position> {
-    let mut nmea = Nmea::default();
+pub fn get_positions(filter: &FixFilter) -> Vec<Position> {
     let mut positions: Vec<Position> = Vec::new();
 
-    let paths = std::fs::read_dir(WORKING_DIR.lock().unwrap().as_str()).unwrap();
-    for path in paths {
-        let path = path.unwrap().path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("nmea") {
-            let mut input = BufReader::new(File::open(path).unwrap());
-            loop {
-                let mut buffer = String::new();
-                let size = input.read_line(&mut buffer).unwrap();
-
-                if size == 0 {
+    let nmea_paths = input_files(InputKind::Nmea)
+        .into_iter()
+        .map(|p| (p, "NMEA"));
+    let gpsd_paths = input_files(InputKind::Gpsd)
+        .into_iter()
+        .map(|p| (p, "gpsd"));
+
+    for (path, format) in nmea_paths.chain(gpsd_paths) {
+        let mut nmea_state = NmeaState::default();
+        let mut gpsd_state = GpsdState::default();
+        let mut input = match open_input(&path) {
+            Ok(input) => input,
+            Err(e) => {
+                error!("Failed to open {} file {:?}: {}", format, path, e);
+                continue;
+            }
+        };
+        loop {
+            let mut buffer = String::new();
+            let size = match input.read_line(&mut buffer) {
+                Ok(size) => size,
+                Err(e) => {
+                    error!("Error reading {} file {:?}: {}", format, path, e);
                     break;
                 }
+            };
 
-                if buffer.starts_with("{") {
-                    continue;
-                }
+            if size == 0 {
+                break;
+            }
 
-                let res = nmea.parse(&buffer);
-                if res.is_err() {
-                    continue;
-                }
-                res.unwrap();
-
-                if let Some(fix_time) = nmea.fix_time {
-                    if let Some(latitude) = nmea.latitude {
-                        if let Some(longitude) = nmea.longitude {
-                            if let Some(fix_date) = nmea.fix_date {
-                                let datetime = NaiveDateTime::new(
-                                    NaiveDate::from_ymd_opt(
-                                        fix_date.year() as i32,
-                                        fix_date.month() as u32,
-                                        fix_date.day() as u32,
-                                    )
-                                    .unwrap(),
-                                    fix_time,
-                                );
-
-                                let timestamp = datetime.and_utc().timestamp();
-
-                                positions.push(Position {
-                                    latitude,
-                                    longitude,
-                                    timestamp,
-                                });
-                            }
-                        }
-                    }
+            if buffer.starts_with("{") {
+                if let Some(position) = parse_gpsd_line(&buffer, &mut gpsd_state) {
+                    positions.push(position);
                 }
+                continue;
+            }
+
+            if let Some(position) = parse_nmea_line(&buffer, &mut nmea_state) {
+                positions.push(position);
+            }
+        }
+
+        positions.extend(nmea_state.finish());
+        if nmea_state.checksum_errors > 0 {
+            warn!(
+                "Ignored {} NMEA sentences with a bad checksum in {:?}",
+                nmea_state.checksum_errors, path
+            );
+        }
+        if nmea_state.undated_fixes > 0 {
+            warn!(
+                "Skipped {} fixes without a date (no RMC or ZDA sentence yet) in {:?}",
+                nmea_state.undated_fixes, path
+            );
+        }
+    }
+
+    for path in input_files(InputKind::Gpx) {
+        let mut content = String::new();
+        if let Err(e) = open_input(&path).and_then(|mut input| input.read_to_string(&mut content)) {
+            error!("Failed to read GPX file {:?}: {}", path, e);
+            continue;
+        }
+
+        match parse_gpx(&content) {
+            Ok(track) => {
+                debug!("Read {} track points from {:?}", track.len(), path);
+                positions.extend(track);
             }
+            Err(e) => error!("Failed to parse GPX file {:?}: {}", path, e),
         }
     }
 
-    positions.sort_by_key(|p| p.timestamp);
+    let total = positions.len();
+    positions.retain(|p| filter.accepts(p));
+    if positions.len() < total {
+        info!("Rejected {} poor GPS fixes", total - positions.len());
+    }
+
+    positions.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
 
     positions
 }
+
+#[cfg(test)]
+mod tests_geo {
+    use super::*;
+
+    #[test]
+    fn test_get_position_at_sub_second() {
+        let positions = vec![
+            Position {
+                latitude: 48.0,
+                longitude: 2.0,
+                timestamp: 1_790_856_000.0,
+                ..Default::default()
+            },
+            Position {
+                latitude: 48.001,
+                longitude: 2.001,
+                timestamp: 1_790_856_000.5,
+                ..Default::default()
+            },
+        ];
+
+        let position = get_position_at(
+            &Duration::from_millis(1_790_856_000_250),
+            &positions,
+            f64::INFINITY,
+        )
+        .unwrap();
+        assert!((position.latitude - 48.0005).abs() < 1e-9);
+        assert!((position.longitude - 2.0005).abs() < 1e-9);
+        assert_eq!(position.timestamp, 1_790_856_000.25);
+
+        assert_eq!(
+            get_position_at(
+                &Duration::from_millis(1_790_856_000_750),
+                &positions,
+                f64::INFINITY
+            )
+            .unwrap_err(),
+            PositionMiss::OutOfRange
+        );
+    }
+
+    #[test]
+    fn test_get_position_at_gap() {
+        let fix = |timestamp| Position {
+            timestamp,
+            ..Default::default()
+        };
+        let positions = vec![
+            fix(1_790_856_000.0),
+            fix(1_790_856_001.0),
+            fix(1_790_856_032.0),
+        ];
+
+        assert!(get_position_at(&Duration::from_secs(1_790_856_001), &positions, 30.0).is_ok());
+        assert_eq!(
+            get_position_at(&Duration::from_secs(1_790_856_010), &positions, 30.0).unwrap_err(),
+            PositionMiss::Gap
+        );
+        assert_eq!(
+            get_position_at(&Duration::from_secs(1_790_856_010), &[], 30.0).unwrap_err(),
+            PositionMiss::NoFix
+        );
+
+        assert!(
+            get_position_at(
+                &Duration::from_secs(1_790_856_010),
+                &positions,
+                f64::INFINITY
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_fix_filter() {
+        let filter = FixFilter {
+            min_quality: Some(FixQuality::Gps),
+            max_hdop: Some(5.0),
+            min_satellites: Some(4),
+        };
+        let fix = Position {
+            hdop: Some(1.2),
+            satellites: Some(7),
+            fix_quality: Some(FixQuality::Dgps),
+            ..Default::default()
+        };
+
+        assert!(filter.accepts(&fix));
+        assert!(filter.accepts(&Position::default()));
+        assert!(!filter.accepts(&Position {
+            hdop: Some(12.0),
+            ..fix.clone()
+        }));
+        assert!(!filter.accepts(&Position {
+            satellites: Some(3),
+            ..fix.clone()
+        }));
+        assert!(!filter.accepts(&Position {
+            fix_quality: Some(FixQuality::Estimated),
+            ..fix.clone()
+        }));
+        assert!(!FixFilter::default().accepts(&Position {
+            fix_quality: Some(FixQuality::Invalid),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_uncertainty_weight() {
+        let fix = |hdop| Position {
+            hdop,
+            ..Default::default()
+        };
+
+        assert_eq!(fix(None).uncertainty_weight(), 1.0);
+        assert_eq!(fix(Some(0.8)).uncertainty_weight(), 1.0);
+        assert!((fix(Some(10.0)).uncertainty_weight() - 0.01).abs() < 1e-12);
+        // gpsd's own error estimate wins over the HDOP
+        let position = Position {
+            eph: Some(5.0),
+            ..fix(Some(10.0))
+        };
+        assert_eq!(position.uncertainty_weight(), 1.0);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::geo::{FixQuality, Position};
+use chrono::DateTime;
+use log::trace;
+use serde_json::Value;
+
+/// State carried between the reports of a `gpspipe -w` log.
+#[derive(Debug, Default)]
+pub struct GpsdState {
+    /// satellites used in the solution, from the last SKY report
+    satellites: Option<u32>,
+    /// horizontal dilution of precision, from the last SKY report
+    hdop: Option<f64>,
+}
+
+/// Parses one line of gpsd JSON. TPV reports with a 2D/3D fix become positions, SKY reports
+/// update the satellite count applied to the following TPVs. Other classes are ignored.
+pub fn parse_gpsd_line(line: &str, state: &mut GpsdState) -> Option<Position> {
+    let report: Value = serde_json::from_str(line.trim()).ok()?;
+
+    match report.get("class")?.as_str()? {
+        "TPV" => parse_tpv(&report, state),
+        "SKY" => {
+            parse_sky(&report, state);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn parse_tpv(report: &Value, state: &GpsdState) -> Option<Position> {
+    // mode 0 = unknown, 1 = no fix, 2 = 2D, 3 = 3D
+    let mode = report.get("mode").and_then(Value::as_u64).unwrap_or(0);
+    if mode < 2 {
+        trace!("Skipping gpsd TPV without fix (mode {})", mode);
+        return None;
+    }
+
+    let latitude = report.get("lat")?.as_f64()?;
+    let longitude = report.get("lon")?.as_f64()?;
+    let time = DateTime::parse_from_rfc3339(report.get("time")?.as_str()?).ok()?;
+
+    let number = |key: &str| report.get(key).and_then(Value::as_f64);
+
+    Some(Position {
+        latitude,
+        longitude,
+        timestamp: time.timestamp_micros() as f64 / 1e6,
+        // gpsd >= 3.20 splits alt into altMSL/altHAE, alt is the deprecated MSL value
+        altitude: if mode == 3 {
+            number("altMSL").or_else(|| number("alt"))
+        } else {
+            None
+        },
+        speed: number("speed"),
+        track: number("track"),
+        eph: number("eph"),
+        epx: number("epx"),
+        epy: number("epy"),
+        hdop: state.hdop,
+        satellites: state.satellites,
+        fix_quality: Some(fix_quality(report)),
+    })
+}
+
+/// Maps the TPV `status`, absent on plain GPS fixes.
+fn fix_quality(report: &Value) -> FixQuality {
+    match report.get("status").and_then(Value::as_u64) {
+        // DGPS
+        Some(2) => FixQuality::Dgps,
+        // RTK fixed, RTK float
+        Some(3 | 4) => FixQuality::Rtk,
+        // dead reckoning, GNSS + dead reckoning, surveyed, simulated
+        Some(5..=8) => FixQuality::Estimated,
+        _ => FixQuality::Gps,
+    }
+}
+
+fn parse_sky(report: &Value, state: &mut GpsdState) {
+    let used_satellites = report.get("uSat").and_then(Value::as_u64).or_else(|| {
+        let satellites = report.get("satellites")?.as_array()?;
+        Some(
+            satellites
+                .iter()
+                .filter(|sat| sat.get("used").and_then(Value::as_bool) == Some(true))
+                .count() as u64,
+        )
+    });
+
+    // gpsd sends partial SKY reports without the satellite list, keep the last known count
+    if let Some(used_satellites) = used_satellites {
+        state.satellites = Some(used_satellites as u32);
+    }
+    if let Some(hdop) = report.get("hdop").and_then(Value::as_f64) {
+        state.hdop = Some(hdop);
+    }
+}
+
+#[cfg(test)]
+mod tests_gpsd {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpsd_line() {
+        let mut state = GpsdState::default();
+
+        let sky = r#"{"class":"SKY","device":"/dev/ttyUSB0","satellites":[{"PRN":5,"used":true},{"PRN":7,"used":false},{"PRN":9,"used":true}]}"#;
+        assert!(parse_gpsd_line(sky, &mut state).is_none());
+        assert_eq!(state.satellites, Some(2));
+
+        let tpv = r#"{"class":"TPV","device":"/dev/ttyUSB0","mode":3,"time":"2026-10-01T12:00:00.000Z","ept":0.005,"lat":48.8566,"lon":2.3522,"altHAE":82.1,"altMSL":35.4,"epx":3.1,"epy":4.2,"eph":5.3,"track":90.5,"speed":12.3}"#;
+        let position = parse_gpsd_line(tpv, &mut state).unwrap();
+        assert_eq!(position.timestamp, 1_790_856_000.0);
+        assert_eq!(position.latitude, 48.8566);
+        assert_eq!(position.altitude, Some(35.4));
+        assert_eq!(position.speed, Some(12.3));
+        assert_eq!(position.eph, Some(5.3));
+        assert_eq!(position.satellites, Some(2));
+        assert_eq!(position.fix_quality, Some(FixQuality::Gps));
+
+        let no_fix = r#"{"class":"TPV","mode":1,"time":"2026-10-01T12:00:01.000Z"}"#;
+        assert!(parse_gpsd_line(no_fix, &mut state).is_none());
+        assert!(parse_gpsd_line(r#"{"class":"VERSION","release":"3.25"}"#, &mut state).is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::geo::{FixQuality, Position};
+use chrono::DateTime;
+use log::{trace, warn};
+use roxmltree::{Document, Node};
+
+/// Reads the track points (`trk/trkseg/trkpt`) of a GPX document. Points without a `<time>` can't
+/// be matched against packets and are skipped.
+pub fn parse_gpx(content: &str) -> Result<Vec<Position>, roxmltree::Error> {
+    let document = Document::parse(content)?;
+    let mut positions = Vec::new();
+    let mut untimed_points = 0;
+
+    for point in document
+        .descendants()
+        .filter(|n| n.is_element() && n.tag_name().name() == "trkpt")
+    {
+        let (Some(latitude), Some(longitude)) = (
+            point.attribute("lat").and_then(|v| v.trim().parse().ok()),
+            point.attribute("lon").and_then(|v| v.trim().parse().ok()),
+        ) else {
+            trace!("Skipping GPX track point without coordinates");
+            continue;
+        };
+
+        let Some(time) =
+            child_text(&point, "time").and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        else {
+            untimed_points += 1;
+            continue;
+        };
+
+        positions.push(Position {
+            latitude,
+            longitude,
+            timestamp: time.timestamp_micros() as f64 / 1e6,
+            altitude: child_text(&point, "ele").and_then(|v| v.parse().ok()),
+            hdop: child_text(&point, "hdop").and_then(|v| v.parse().ok()),
+            satellites: child_text(&point, "sat").and_then(|v| v.parse().ok()),
+            fix_quality: child_text(&point, "fix").and_then(parse_fix),
+            ..Default::default()
+        });
+    }
+
+    if untimed_points > 0 {
+        warn!(
+            "Skipped {} GPX track points without a timestamp",
+            untimed_points
+        );
+    }
+
+    Ok(positions)
+}
+
+/// Maps a GPX `<fix>` value (none, 2d, 3d, dgps, pps).
+fn parse_fix(fix: &str) -> Option<FixQuality> {
+    match fix {
+        "none" => Some(FixQuality::Invalid),
+        "2d" | "3d" => Some(FixQuality::Gps),
+        "dgps" | "pps" => Some(FixQuality::Dgps),
+        _ => None,
+    }
+}
+
+fn child_text<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests_gpx {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpx() {
+        let gpx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="GPSLogger" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><name>drive</name><trkseg>
+    <trkpt lat="48.8566" lon="2.3522"><ele>35.4</ele><time>2026-10-01T12:00:00Z</time><fix>dgps</fix><hdop>0.9</hdop><sat>8</sat></trkpt>
+    <trkpt lat="48.8567" lon="2.3523"><time>2026-10-01T12:00:01.500Z</time></trkpt>
+    <trkpt lat="48.8568" lon="2.3524"><ele>36.0</ele></trkpt>
+  </trkseg></trk>
+</gpx>"#;
+
+        let positions = parse_gpx(gpx).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].timestamp, 1_790_856_000.0);
+        assert_eq!(positions[1].timestamp, 1_790_856_001.5);
+        assert_eq!(positions[0].altitude, Some(35.4));
+        assert_eq!(positions[0].hdop, Some(0.9));
+        assert_eq!(positions[0].satellites, Some(8));
+        assert_eq!(positions[0].fix_quality, Some(FixQuality::Dgps));
+        assert_eq!(positions[1].longitude, 2.3523);
+        assert_eq!(positions[1].altitude, None);
+    }
+}
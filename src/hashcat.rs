@@ -1,20 +1,22 @@
-use crate::packets::WifiSecurity;
-use crate::{AccessPoint, WORKING_DIR};
+use crate::AccessPoint;
+use crate::input::{InputKind, input_files, is_compressed, open_input};
+use crate::security::WifiSecurity;
+use crate::ssid::{Ssid, decode_hex};
 use log::{debug, error, trace, warn};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
 
 struct APPassword {
     mac: [u8; 6],
-    ssid: String,
+    ssid: Ssid,
     password: String,
     security: WifiSecurity,
 }
 
-pub fn bind_passwords_to_aps(aps: &mut Vec<AccessPoint>) {
+pub fn bind_passwords_to_aps(aps: &mut [AccessPoint]) {
     let passwords = get_passwords();
     for ap in aps.iter_mut() {
         for pwd in &passwords {
@@ -25,7 +27,7 @@ pub fn bind_passwords_to_aps(aps: &mut Vec<AccessPoint>) {
                     if ap.security.is_none() {
                         ap.security = Some(pwd.security.clone());
                     }
-                } else if ap.ssid.as_ref().unwrap() == &pwd.ssid {
+                } else if ap.ssid.as_ref() == Some(&pwd.ssid) {
                     ap.password = Some(pwd.password.clone());
                     if ap.security.is_none() {
                         ap.security = Some(pwd.security.clone());
@@ -46,20 +48,10 @@ fn get_passwords() -> Vec<APPassword> {
     }
     let hashcat_bin = hashcat_bin.unwrap();
 
-    let hash_files: Vec<PathBuf> = match fs::read_dir(WORKING_DIR.lock().unwrap().as_str()) {
-        Ok(entries) => entries
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("22000"))
-            .collect(),
-        Err(e) => {
-            error!("Failed to read dumps directory: {}", e);
-            return passwords;
-        }
-    };
+    let hash_files: Vec<PathBuf> = input_files(InputKind::Hashcat);
 
     if hash_files.is_empty() {
-        warn!("No .22000 files found in dumps directory.");
+        warn!("No .22000 files found in input paths.");
         return passwords;
     }
 
@@ -68,10 +60,26 @@ fn get_passwords() -> Vec<APPassword> {
     let mut seen_hashes = HashSet::new();
 
     for file in &hash_files {
-        let mut cmd = Command::new(&hashcat_bin);
-        cmd.arg("--show").arg("-m").arg("22000").arg(file);
+        // hashcat cannot read compressed hash files, hand it a decompressed copy instead
+        let hash_file = match decompressed_copy(file) {
+            Ok(hash_file) => hash_file,
+            Err(e) => {
+                error!("Failed to decompress hash file {:?}: {}", file, e);
+                continue;
+            }
+        };
 
-        let output = match cmd.output() {
+        let mut cmd = Command::new(&hashcat_bin);
+        cmd.arg("--show")
+            .arg("-m")
+            .arg("22000")
+            .arg(hash_file.as_deref().unwrap_or(file));
+
+        let output = cmd.output();
+        if let Some(ref hash_file) = hash_file {
+            let _ = fs::remove_file(hash_file);
+        }
+        let output = match output {
             Ok(output) => output,
             Err(e) => {
                 error!("Failed to execute hashcat for file {:?}: {}", file, e);
@@ -87,10 +95,12 @@ fn get_passwords() -> Vec<APPassword> {
                 }
                 seen_hashes.insert(line.to_string());
 
-                let parts: Vec<&str> = line.split(':').collect();
+                // the password may contain ':' itself
+                let parts: Vec<&str> = line.splitn(5, ':').collect();
                 if parts.len() >= 5 {
                     let mac_str = parts[1];
-                    let ssid = parts[3].to_string();
+                    // non-printable ESSIDs are $HEX[...] encoded
+                    let ssid = Ssid::from_hashcat(parts[3]);
                     let password = parts[4].to_string();
                     if mac_str.len() == 12 {
                         let mut mac = [0u8; 6];
@@ -126,19 +136,66 @@ fn get_passwords() -> Vec<APPassword> {
     passwords
 }
 
-fn parse_22000_files(files: &[PathBuf]) -> std::collections::HashMap<[u8; 6], WifiSecurity> {
-    use std::collections::HashMap;
+/// Decompresses a gzip/xz hash file into the temp directory, returns `None` if the file is
+/// not compressed.
+fn decompressed_copy(file: &Path) -> std::io::Result<Option<PathBuf>> {
+    if !is_compressed(file)? {
+        return Ok(None);
+    }
+
+    let file_name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "hashes.22000".to_string());
+    let copy_path = std::env::temp_dir().join(format!(
+        "{}-{}-{}",
+        env!("CARGO_PKG_NAME"),
+        process::id(),
+        file_name
+    ));
+
+    let mut input = open_input(file)?;
+    let mut output = fs::File::create(&copy_path)?;
+    std::io::copy(&mut input, &mut output)?;
+    trace!("Decompressed {:?} to {:?}", file, copy_path);
+
+    Ok(Some(copy_path))
+}
+
+/// Names the hidden access points that are still unnamed after the ESSIDs of their hashes,
+/// cracked or not. Returns the number of revealed SSIDs.
+pub fn reveal_hidden_ssids(aps: &mut [AccessPoint]) -> usize {
+    let mut essids: HashMap<[u8; 6], Ssid> = HashMap::new();
+    for file_path in input_files(InputKind::Hashcat) {
+        if let Ok(reader) = open_input(&file_path) {
+            for line in reader.lines().map_while(Result::ok) {
+                if let Some((mac, essid)) = parse_22000_essid(&line) {
+                    essids.entry(mac).or_insert(essid);
+                }
+            }
+        }
+    }
+
+    let mut revealed = 0;
+    for ap in aps.iter_mut().filter(|ap| ap.hidden && ap.ssid.is_none()) {
+        if let Some(essid) = essids.get(&ap.mac) {
+            trace!("Revealed hidden SSID {} for {:02x?}", essid, ap.mac);
+            ap.ssid = Some(essid.clone());
+            revealed += 1;
+        }
+    }
+    revealed
+}
+
+fn parse_22000_files(files: &[PathBuf]) -> HashMap<[u8; 6], WifiSecurity> {
     let mut security_map = HashMap::new();
 
     for file_path in files {
-        if let Ok(file) = fs::File::open(file_path) {
+        if let Ok(reader) = open_input(file_path) {
             debug!("Parsing security info from file {:?}", file_path);
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if let Some(security_info) = parse_22000_line(&line) {
-                        security_map.insert(security_info.0, security_info.1);
-                    }
+            for line in reader.lines().map_while(Result::ok) {
+                if let Some(security_info) = parse_22000_line(&line) {
+                    security_map.insert(security_info.0, security_info.1);
                 }
             }
         }
@@ -158,15 +215,7 @@ fn parse_22000_line(line: &str) -> Option<([u8; 6], WifiSecurity)> {
 
     let capture_type = parts[1]; // 01 = PMKID, 02 = EAPOL
 
-    let mac_str = parts[3];
-    if mac_str.len() != 12 {
-        return None;
-    }
-
-    let mut mac = [0u8; 6];
-    for i in 0..6 {
-        mac[i] = u8::from_str_radix(&mac_str[i * 2..i * 2 + 2], 16).ok()?;
-    }
+    let mac = parse_22000_mac(parts[3])?;
 
     let security = if capture_type == "02" && parts.len() >= 9 {
         // TYPE 02 = EAPOL handshake, analyse EAPOL (index 7)
@@ -179,6 +228,31 @@ fn parse_22000_line(line: &str) -> Option<([u8; 6], WifiSecurity)> {
     Some((mac, security))
 }
 
+fn parse_22000_essid(line: &str) -> Option<([u8; 6], Ssid)> {
+    let parts: Vec<&str> = line.split('*').collect();
+    if parts.len() < 6 || parts[0] != "WPA" {
+        return None;
+    }
+
+    let mac = parse_22000_mac(parts[3])?;
+    // the ESSID is hex encoded
+    let essid = decode_hex(parts[5]).filter(|essid| !essid.is_empty())?;
+
+    Some((mac, Ssid::new(&essid)))
+}
+
+fn parse_22000_mac(mac_str: &str) -> Option<[u8; 6]> {
+    if mac_str.len() != 12 {
+        return None;
+    }
+
+    let mut mac = [0u8; 6];
+    for i in 0..6 {
+        mac[i] = u8::from_str_radix(&mac_str[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(mac)
+}
+
 fn parse_security_from_eapol(eapol: &str) -> WifiSecurity {
     // 000fac01 = WPA (802.1X)
     // 000fac02 = WPA2-PSK (le plus commun)
@@ -0,0 +1,285 @@
+use crate::INPUT_PATHS;
+use flate2::read::MultiGzDecoder;
+use log::{debug, error, trace, warn};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Result as IoResult};
+use std::path::{Path, PathBuf};
+use xz2::read::XzDecoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const XZ_MAGIC: [u8; 6] = [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+
+/// Every input file of the run, discovered once from the `-d` arguments so all loaders agree.
+static INPUT_FILES: Lazy<Vec<InputFile>> =
+    Lazy::new(|| discover_inputs(&INPUT_PATHS.lock().unwrap()));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    Capture,
+    Nmea,
+    Gpsd,
+    Gpx,
+    Hashcat,
+}
+
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub kind: InputKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Xz,
+}
+
+fn detect_compression(magic: &[u8]) -> Compression {
+    if magic.starts_with(&GZIP_MAGIC) {
+        Compression::Gzip
+    } else if magic.starts_with(&XZ_MAGIC) {
+        Compression::Xz
+    } else {
+        Compression::None
+    }
+}
+
+/// Returns true if the file is gzip or xz compressed, based on its first bytes.
+pub fn is_compressed(path: &Path) -> IoResult<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(detect_compression(reader.fill_buf()?) != Compression::None)
+}
+
+/// Opens a file as a buffered stream, transparently decompressing gzip and xz content.
+pub fn open_input(path: &Path) -> IoResult<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    match detect_compression(reader.fill_buf()?) {
+        Compression::Gzip => {
+            trace!("Decompressing gzip stream from {:?}", path);
+            // rotated logs are often several gzip members appended to each other
+            Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+        }
+        Compression::Xz => {
+            trace!("Decompressing xz stream from {:?}", path);
+            Ok(Box::new(BufReader::new(XzDecoder::new_multi_decoder(
+                reader,
+            ))))
+        }
+        Compression::None => Ok(Box::new(reader)),
+    }
+}
+
+/// Returns the extension of a file, ignoring a trailing `.gz` or `.xz` suffix
+/// (`dump.pcapng.gz` -> `pcapng`).
+pub fn inner_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if extension == "gz" || extension == "xz" {
+        let stem = Path::new(path.file_stem()?);
+        return Some(stem.extension()?.to_str()?.to_ascii_lowercase());
+    }
+    Some(extension)
+}
+
+/// Kind of an input file from its extension. `.json` files found while searching directories
+/// are only taken as gpsd logs when they look like one, `explicit` files are trusted.
+fn input_kind(path: &Path, explicit: bool) -> Option<InputKind> {
+    match inner_extension(path)?.as_str() {
+        "pcapng" | "pcap" | "cap" => Some(InputKind::Capture),
+        "nmea" => Some(InputKind::Nmea),
+        // gpspipe -w logs
+        "gpsd" => Some(InputKind::Gpsd),
+        "json" if explicit || is_gpsd_log(path) => Some(InputKind::Gpsd),
+        "gpx" => Some(InputKind::Gpx),
+        "22000" => Some(InputKind::Hashcat),
+        _ => None,
+    }
+}
+
+/// gpsd JSON reports all have a `class` field, e.g. `{"class":"TPV",...}`.
+fn is_gpsd_log(path: &Path) -> bool {
+    let Ok(reader) = open_input(path) else {
+        return false;
+    };
+    reader
+        .lines()
+        .take(10)
+        .map_while(Result::ok)
+        .any(|line| line.contains("\"class\""))
+}
+
+/// Returns the input files of the given kind, in a stable order.
+pub fn input_files(kind: InputKind) -> Vec<PathBuf> {
+    INPUT_FILES
+        .iter()
+        .filter(|file| file.kind == kind)
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+/// Resolves directories (recursively), explicit files and glob patterns into the list of
+/// input files.
+pub fn discover_inputs(patterns: &[String]) -> Vec<InputFile> {
+    // keyed by canonical path so a file reached twice (overlapping -d, symlinks) is read once
+    let mut files: BTreeMap<PathBuf, InputFile> = BTreeMap::new();
+    let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+
+    for pattern in patterns {
+        if pattern.contains(['*', '?', '[']) {
+            let entries = match glob::glob(pattern) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("Invalid glob pattern {}: {}", pattern, e);
+                    continue;
+                }
+            };
+
+            let mut matched = false;
+            for entry in entries {
+                match entry {
+                    Ok(path) => {
+                        matched = true;
+                        collect_path(&path, false, &mut files, &mut visited_dirs);
+                    }
+                    Err(e) => warn!("Cannot read {:?}: {}", e.path(), e.error()),
+                }
+            }
+            if !matched {
+                warn!("Glob pattern {} did not match anything", pattern);
+            }
+        } else {
+            let path = Path::new(pattern);
+            if path.exists() {
+                collect_path(path, true, &mut files, &mut visited_dirs);
+            } else {
+                error!("Input path {} does not exist", pattern);
+            }
+        }
+    }
+
+    files.into_values().collect()
+}
+
+fn collect_path(
+    path: &Path,
+    explicit: bool,
+    files: &mut BTreeMap<PathBuf, InputFile>,
+    visited_dirs: &mut HashSet<PathBuf>,
+) {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    if path.is_dir() {
+        // guards against symlink loops
+        if !visited_dirs.insert(canonical) {
+            return;
+        }
+
+        debug!("Searching directory {:?}", path);
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Failed to read directory {:?}: {}", path, e);
+                return;
+            }
+        };
+
+        let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        children.sort();
+        for child in children {
+            let hidden = child
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if !hidden {
+                collect_path(&child, false, files, visited_dirs);
+            }
+        }
+    } else if path.is_file() {
+        if let Some(kind) = input_kind(path, explicit) {
+            trace!("Found input file {:?}", path);
+            files.entry(canonical).or_insert_with(|| InputFile {
+                path: path.to_path_buf(),
+                kind,
+            });
+        } else if explicit {
+            warn!("Ignoring {:?}: unknown file type", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_input {
+    use super::*;
+
+    #[test]
+    fn test_inner_extension() {
+        assert_eq!(
+            inner_extension(Path::new("dumps/a.pcapng")).as_deref(),
+            Some("pcapng")
+        );
+        assert_eq!(
+            inner_extension(Path::new("dumps/a.pcapng.gz")).as_deref(),
+            Some("pcapng")
+        );
+        assert_eq!(
+            inner_extension(Path::new("dumps/a.NMEA.xz")).as_deref(),
+            Some("nmea")
+        );
+        assert_eq!(inner_extension(Path::new("dumps/a.gz")), None);
+    }
+
+    #[test]
+    fn test_discover_inputs() {
+        let root = std::env::temp_dir().join(format!("hcxmaptool-inputs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub/.hidden")).unwrap();
+        for (file, content) in [
+            ("b.pcapng", ""),
+            ("notes.txt", ""),
+            ("package.json", "{\"name\": \"hcxmaptool\"}"),
+            ("sub/a.nmea.gz", ""),
+            ("sub/track.json", "{\"class\":\"VERSION\"}"),
+            ("sub/.hidden/c.pcap", ""),
+        ] {
+            std::fs::write(root.join(file), content).unwrap();
+        }
+
+        let path = |p: &str| root.join(p).to_string_lossy().into_owned();
+        // overlapping directory and glob, each file is listed once
+        let inputs = discover_inputs(&[path(""), path("sub"), path("*.pcapng")]);
+        let found: Vec<(PathBuf, InputKind)> = inputs
+            .iter()
+            .map(|file| {
+                (
+                    file.path.strip_prefix(&root).unwrap().to_path_buf(),
+                    file.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (PathBuf::from("b.pcapng"), InputKind::Capture),
+                (PathBuf::from("sub/a.nmea.gz"), InputKind::Nmea),
+                (PathBuf::from("sub/track.json"), InputKind::Gpsd),
+            ]
+        );
+
+        // JSON files named explicitly are trusted
+        let inputs = discover_inputs(&[path("package.json")]);
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].kind, InputKind::Gpsd);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(detect_compression(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(detect_compression(&XZ_MAGIC), Compression::Xz);
+        assert_eq!(detect_compression(b"$GPGGA"), Compression::None);
+    }
+}
@@ -1,9 +1,20 @@
-use crate::AccessPoint;
+use crate::channel::Band;
+use crate::eapol::Handshake;
+use crate::phy::{PhyCapabilities, PhyGeneration};
+use crate::security::AuthMethod;
+use crate::signal::{average_chains, average_noise, average_snr};
+use crate::station::Station;
+use crate::wps::WpsInfo;
+use crate::{AccessPoint, format_time};
 use log::{info, warn};
 use std::fs::File;
 use std::io::{Result as IoResult, Write};
 
-pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<()> {
+pub fn export_to_kml(
+    access_points: &[AccessPoint],
+    stations: &[Station],
+    filename: &str,
+) -> IoResult<()> {
     let mut file = File::create(filename)?;
 
     writeln!(file, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
@@ -46,20 +57,58 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
         "security-wpa3",
         "http://maps.google.com/mapfiles/kml/paddle/red-circle.png",
     )?;
+    write_style(
+        &mut file,
+        "security-owe",
+        "http://maps.google.com/mapfiles/kml/paddle/wht-diamond.png",
+    )?;
+    write_style(
+        &mut file,
+        "security-enterprise",
+        "http://maps.google.com/mapfiles/kml/paddle/pink-stars.png",
+    )?;
+    write_style(
+        &mut file,
+        "phy-legacy",
+        "http://maps.google.com/mapfiles/kml/paddle/wht-blank.png",
+    )?;
     write_style(
         &mut file,
         "password-known",
         "http://maps.google.com/mapfiles/kml/paddle/grn-stars.png",
     )?;
+    write_style(
+        &mut file,
+        "station",
+        "http://maps.google.com/mapfiles/kml/shapes/phone.png",
+    )?;
 
     let mut count = 0;
     let mut unknown_ssid_count = 0;
     let mut unknown_security_count = 0;
 
-    for ap in access_points
+    // one folder per band, so each can be toggled on the map
+    let mut located_aps: Vec<&AccessPoint> = access_points
         .iter()
         .filter(|ap| ap.estimated_position.is_some())
-    {
+        .collect();
+    located_aps.sort_by_key(|ap| (ap.band.is_none(), ap.band));
+    let mut folder_band: Option<Option<Band>> = None;
+
+    for ap in located_aps {
+        if folder_band != Some(ap.band) {
+            if folder_band.is_some() {
+                writeln!(file, "    </Folder>")?;
+            }
+            let name = ap
+                .band
+                .map(|band| band.to_string())
+                .unwrap_or_else(|| "Unknown band".to_string());
+            writeln!(file, "    <Folder>")?;
+            writeln!(file, "      <name>{}</name>", name)?;
+            folder_band = Some(ap.band);
+        }
+
         if let Some(ref pos) = ap.estimated_position {
             let mac = format!(
                 "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
@@ -70,7 +119,7 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
             let ssid = ap
                 .ssid
                 .as_ref()
-                .map(|s| sanitize_for_xml(s))
+                .map(|s| sanitize_for_xml(&s.to_string()))
                 .unwrap_or_else(|| {
                     unknown_ssid_count += 1;
                     format!("Unknown SSID {}", unknown_ssid_count)
@@ -97,15 +146,28 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
             };
 
             // determine style based on security and password presence
+            let auth_methods = ap.auth_methods();
             let style = if ap.password.is_some() {
                 "password-known"
+            } else if ap.is_enterprise() {
+                "security-enterprise"
+            } else if auth_methods == [AuthMethod::Owe] {
+                "security-owe"
             } else {
                 match ap.security.as_ref().map(|s| s.to_string()).as_deref() {
                     Some("Open") => "security-open",
                     Some("WEP") => "security-wep",
-                    Some("WPA") => "security-wpa",
+                    Some("WPA") | Some("WPA/WPA2") => "security-wpa",
                     Some("WPA2") => "security-wpa2",
                     Some("WPA3") | Some("WPA2/WPA3") => "security-wpa3",
+                    // 802.11b-only relics, when the security doesn't already tell them apart
+                    _ if ap
+                        .phy
+                        .as_ref()
+                        .is_some_and(|phy| phy.generation == PhyGeneration::B) =>
+                    {
+                        "phy-legacy"
+                    }
                     _ => "security-unknown",
                 }
             };
@@ -114,7 +176,46 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
             writeln!(file, "      <name>{}</name>", ssid)?;
             writeln!(file, "      <description>")?;
             writeln!(file, "SSID: {}", ssid)?;
+            if ap.hidden {
+                let revealed = if ap.ssid.is_some() {
+                    "revealed"
+                } else {
+                    "not revealed"
+                };
+                writeln!(file, "Hidden SSID: yes ({})", revealed)?;
+            }
             writeln!(file, "Security: {}", security)?;
+            if !auth_methods.is_empty() {
+                let auth: Vec<String> = auth_methods.iter().map(|m| m.to_string()).collect();
+                writeln!(file, "Auth: {}", auth.join(", "))?;
+            }
+            if !ap.rsn.akms.is_empty() {
+                let akms: Vec<String> = ap.rsn.akms.iter().map(|a| a.to_string()).collect();
+                writeln!(file, "AKMs: {}", akms.join(", "))?;
+            }
+            if let Some(group_cipher) = ap.rsn.group_cipher {
+                let pairwise: Vec<String> = ap
+                    .rsn
+                    .pairwise_ciphers
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect();
+                writeln!(
+                    file,
+                    "Ciphers: {} (group {})",
+                    pairwise.join(", "),
+                    group_cipher
+                )?;
+            }
+            if let Some(pmf) = ap.rsn.pmf {
+                writeln!(file, "PMF: {}", pmf)?;
+            }
+            if ap.rsn.tkip_only() {
+                writeln!(file, "Weakness: TKIP only")?;
+            }
+            if ap.lacks_pmf() {
+                writeln!(file, "Weakness: no PMF")?;
+            }
             writeln!(file, "MAC: {}", mac)?;
             if let Some(ref password) = ap.password {
                 writeln!(file, "Password: {}", sanitize_for_xml(password))?;
@@ -135,9 +236,65 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
                 "Signal: {} to {} dBm (avg {:.1})",
                 min_rssi, max_rssi, avg_rssi
             )?;
+            if let Some(noise) = average_noise(&ap.observations) {
+                writeln!(file, "Noise: {:.1} dBm (avg)", noise)?;
+            }
+            if let Some(snr) = average_snr(&ap.observations) {
+                writeln!(file, "SNR: {:.1} dB (avg)", snr)?;
+            }
+            let chains: Vec<String> = average_chains(&ap.observations)
+                .iter()
+                .map(|(antenna, signal)| format!("antenna {} {:.1} dBm", antenna, signal))
+                .collect();
+            if !chains.is_empty() {
+                writeln!(file, "Chains: {}", chains.join(", "))?;
+            }
+            if let Some(ref handshake) = ap.handshake {
+                write_handshake(&mut file, handshake)?;
+            }
+            if let Some(ref wps) = ap.wps {
+                write_wps(&mut file, wps)?;
+            }
             if let Some(channel) = ap.channel {
                 writeln!(file, "Channel: {}", channel)?;
             }
+            if let Some(band) = ap.band {
+                writeln!(file, "Band: {}", band)?;
+            }
+            if let Some(ref phy) = ap.phy {
+                write_phy(&mut file, phy)?;
+            }
+            if let Some(country) = ap.regulatory.country_name() {
+                writeln!(file, "Country: {}", sanitize_for_xml(&country))?;
+            }
+            if let Some(tx_power) = ap.regulatory.tx_power {
+                writeln!(file, "TX power: {} dBm", tx_power)?;
+            }
+            for violation in ap.regulatory_violations() {
+                writeln!(file, "Regulatory violation: {}", violation)?;
+            }
+            let off_channel = ap.off_channel_observations();
+            if off_channel > 0 {
+                writeln!(file, "Off-channel observations: {}", off_channel)?;
+            }
+            if let Some(operating_channel) = ap.operating_channel {
+                match operating_channel.center_channels() {
+                    Some(center) => writeln!(
+                        file,
+                        "Width: {} (center {})",
+                        operating_channel.width, center
+                    )?,
+                    None => writeln!(file, "Width: {}", operating_channel.width)?,
+                }
+            }
+            writeln!(
+                file,
+                "Interfaces: {}",
+                sanitize_for_xml(&ap.interfaces().join(", "))
+            )?;
+            if !ap.clients.is_empty() {
+                writeln!(file, "Clients: {}", ap.clients.len())?;
+            }
             writeln!(file, "      </description>")?;
             writeln!(file, "      <styleUrl>#{}</styleUrl>", style)?;
             writeln!(file, "      <Point>")?;
@@ -153,6 +310,12 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
         }
     }
 
+    if folder_band.is_some() {
+        writeln!(file, "    </Folder>")?;
+    }
+
+    let station_count = write_stations(&mut file, stations)?;
+
     writeln!(file, "  </Document>")?;
     writeln!(file, "</kml>")?;
 
@@ -170,8 +333,158 @@ pub fn export_to_kml(access_points: &[AccessPoint], filename: &str) -> IoResult<
         );
     }
 
-    info!("Exported {} access points to {}", count, filename);
+    info!(
+        "Exported {} access points and {} stations to {}",
+        count, station_count, filename
+    );
+
+    Ok(())
+}
+
+/// Writes the stations heard at a known position in their own folder, placed where their
+/// signal was the strongest. Returns the number of stations written.
+fn write_stations(file: &mut File, stations: &[Station]) -> IoResult<usize> {
+    let mut count = 0;
+
+    for station in stations {
+        let Some(observation) = station.strongest_observation() else {
+            continue;
+        };
+        if count == 0 {
+            writeln!(file, "    <Folder>")?;
+            writeln!(file, "      <name>Stations</name>")?;
+        }
+        let mac = format!(
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            station.mac[0],
+            station.mac[1],
+            station.mac[2],
+            station.mac[3],
+            station.mac[4],
+            station.mac[5]
+        );
+        let bssids: Vec<String> = station
+            .bssids
+            .iter()
+            .map(|b| {
+                format!(
+                    "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+                    b[0], b[1], b[2], b[3], b[4], b[5]
+                )
+            })
+            .collect();
+
+        writeln!(file, "    <Placemark>")?;
+        writeln!(file, "      <name>{}</name>", mac)?;
+        writeln!(file, "      <description>")?;
+        writeln!(file, "MAC: {}", mac)?;
+        if let Some(ref vendor) = station.vendor {
+            writeln!(file, "Vendor: {}", sanitize_for_xml(vendor))?;
+        }
+        if station.randomized {
+            writeln!(file, "Randomized MAC: yes")?;
+        }
+        writeln!(file, "Observations: {}", station.observations.len())?;
+        writeln!(file, "Signal: {} dBm (max)", observation.signal_strength)?;
+        if !bssids.is_empty() {
+            writeln!(file, "Access points: {}", bssids.join(", "))?;
+        }
+        let probed: Vec<String> = station
+            .preferred_networks()
+            .iter()
+            .map(|(ssid, _)| sanitize_for_xml(&ssid.to_string()))
+            .collect();
+        if !probed.is_empty() {
+            writeln!(file, "Probed: {}", probed.join(", "))?;
+        }
+        writeln!(file, "      </description>")?;
+        writeln!(file, "      <styleUrl>#station</styleUrl>")?;
+        writeln!(file, "      <Point>")?;
+        writeln!(
+            file,
+            "        <coordinates>{:.6},{:.6},0</coordinates>",
+            observation.position.longitude, observation.position.latitude
+        )?;
+        writeln!(file, "      </Point>")?;
+        writeln!(file, "    </Placemark>")?;
+
+        count += 1;
+    }
+    if count > 0 {
+        writeln!(file, "    </Folder>")?;
+    }
+
+    Ok(count)
+}
+
+fn write_phy(file: &mut File, phy: &PhyCapabilities) -> IoResult<()> {
+    writeln!(file, "PHY: {}", phy.generation_name())?;
+    if let Some(streams) = phy.spatial_streams {
+        writeln!(file, "Spatial streams: {}", streams)?;
+    }
+    let widths: Vec<String> = phy.widths.iter().map(|w| w.to_string()).collect();
+    writeln!(file, "Supported widths: {}", widths.join(", "))
+}
 
+fn write_handshake(file: &mut File, handshake: &Handshake) -> IoResult<()> {
+    let status = if handshake.is_crackable() {
+        "captured"
+    } else {
+        "incomplete"
+    };
+    if !handshake.messages.is_empty() {
+        writeln!(
+            file,
+            "Handshake: {} ({})",
+            status,
+            handshake.messages_string()
+        )?;
+    }
+    if handshake.pmkid.is_some() {
+        writeln!(file, "PMKID: captured")?;
+    }
+    if let Some(capture) = handshake.first_capture() {
+        match capture.position {
+            Some(ref pos) => writeln!(
+                file,
+                "Handshake first seen: {} at {:.6}, {:.6}",
+                format_time(&capture.timestamp),
+                pos.latitude,
+                pos.longitude
+            )?,
+            None => writeln!(
+                file,
+                "Handshake first seen: {}",
+                format_time(&capture.timestamp)
+            )?,
+        }
+    }
+    Ok(())
+}
+
+fn write_wps(file: &mut File, wps: &WpsInfo) -> IoResult<()> {
+    let mut status = vec![
+        wps.state
+            .map(|state| state.to_string())
+            .unwrap_or_else(|| "enabled".to_string()),
+    ];
+    if wps.ap_setup_locked == Some(true) {
+        status.push("locked".to_string());
+    }
+    writeln!(file, "WPS: {}", status.join(", "))?;
+
+    let fields = [
+        ("WPS manufacturer", wps.manufacturer.clone()),
+        ("WPS model", wps.model()),
+        ("WPS device name", wps.device_name.clone()),
+        ("WPS serial", wps.serial_number.clone()),
+        ("WPS UUID", wps.uuid_string()),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            writeln!(file, "{}: {}", label, sanitize_for_xml(&value))?;
+        }
+    }
     Ok(())
 }
 
@@ -194,8 +507,8 @@ fn sanitize_for_xml(s: &str) -> String {
             c == '\t'
                 || c == '\n'
                 || c == '\r'
-                || (code >= 0x20 && code <= 0xD7FF)
-                || (code >= 0xE000 && code <= 0xFFFD)
+                || (0x20..=0xD7FF).contains(&code)
+                || (0xE000..=0xFFFD).contains(&code)
         })
         .map(|c| match c {
             '&' => "&amp;".to_string(),
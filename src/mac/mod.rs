@@ -1,22 +1,42 @@
 use crate::AccessPoint;
+use crate::station::Station;
 use log::{debug, trace};
 use std::collections::HashMap;
 
-pub fn bind_vendors_to_aps(aps: &mut Vec<AccessPoint>) {
+pub fn bind_vendors_to_aps(aps: &mut [AccessPoint]) {
     let macs: Vec<&[u8; 6]> = aps.iter().map(|ap| &ap.mac).collect();
     let vendor_map = lookup(macs);
 
     for ap in aps.iter_mut() {
-        if let Some(vendor) = vendor_map.get(&ap.mac) {
-            ap.vendor = Some(vendor.clone());
+        // unknown or locally administered OUIs, the WPS manufacturer is the next best thing
+        let vendor = vendor_map
+            .get(&ap.mac)
+            .cloned()
+            .or_else(|| ap.wps.as_ref().and_then(|wps| wps.manufacturer.clone()));
+        if let Some(vendor) = vendor {
             trace!(
                 "Bound vendor '{}' to AP {:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
                 vendor, ap.mac[0], ap.mac[1], ap.mac[2], ap.mac[3], ap.mac[4], ap.mac[5]
             );
+            ap.vendor = Some(vendor);
         }
     }
 }
 
+pub fn bind_vendors_to_stations(stations: &mut [Station]) {
+    // randomized addresses don't carry a real OUI
+    let macs: Vec<&[u8; 6]> = stations
+        .iter()
+        .filter(|station| !station.randomized)
+        .map(|station| &station.mac)
+        .collect();
+    let vendor_map = lookup(macs);
+
+    for station in stations.iter_mut() {
+        station.vendor = vendor_map.get(&station.mac).cloned();
+    }
+}
+
 fn lookup(macs: Vec<&[u8; 6]>) -> HashMap<[u8; 6], String> {
     let csv_data = include_str!("mac-vendors.csv");
     let mut results: HashMap<[u8; 3], String> = HashMap::new();
@@ -1,38 +1,135 @@
+mod channel;
 mod cli;
+mod clock;
+mod eapol;
 mod geo;
+mod gpsd;
+mod gpx;
 mod hashcat;
+mod input;
 mod kml;
 mod mac;
+mod nmea_log;
 mod packets;
+mod phy;
+mod regulatory;
+mod security;
+mod signal;
+mod ssid;
+mod station;
+mod wps;
 
+use crate::channel::{Band, OperatingChannel};
 use crate::cli::*;
+use crate::clock::apply_time_offsets;
+use crate::eapol::Handshake;
 use crate::geo::*;
-use crate::hashcat::bind_passwords_to_aps;
+use crate::hashcat::{bind_passwords_to_aps, reveal_hidden_ssids};
+use crate::input::{InputKind, input_files};
 use crate::kml::export_to_kml;
-use crate::mac::bind_vendors_to_aps;
+use crate::mac::{bind_vendors_to_aps, bind_vendors_to_stations};
 use crate::packets::*;
+use crate::phy::PhyCapabilities;
+use crate::regulatory::{RegulatoryInfo, RegulatoryViolation};
+use crate::security::{AuthMethod, Pmf, RsnInfo, WifiSecurity, auth_methods};
+use crate::signal::{average_chains, average_noise, average_snr};
+use crate::ssid::Ssid;
+use crate::station::{
+    Station, bind_clients_to_aps, group_packets_by_station, locate_probed_network,
+};
+use crate::wps::WpsInfo;
+use chrono::{DateTime, SecondsFormat};
 use clap::{CommandFactory, Parser};
 use clap_help::Printer;
 use log::{debug, info, trace, warn};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::Write;
 use std::sync::Mutex;
+use std::time::Duration;
 
-static WORKING_DIR: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(".".to_string()));
+static INPUT_PATHS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(vec![".".to_string()]));
 
 #[derive(Debug, Clone)]
 pub struct AccessPoint {
     pub mac: [u8; 6],
-    pub ssid: Option<String>,
+    pub ssid: Option<Ssid>,
     pub observations: Vec<Observation>,
     pub estimated_position: Option<Position>,
     pub position_method: Option<String>,
     pub security: Option<WifiSecurity>,
+    /// ciphers, AKM suites and PMF from the RSN or WPA element
+    pub rsn: RsnInfo,
+    /// device description from the WPS element, if the AP runs WPS
+    pub wps: Option<WpsInfo>,
+    /// country, allowed channels and transmit power advertised by the AP
+    pub regulatory: RegulatoryInfo,
+    /// PHY generation, spatial streams and supported channel widths
+    pub phy: Option<PhyCapabilities>,
+    /// 4-way handshake messages and PMKID captured with this AP's clients
+    pub handshake: Option<Handshake>,
     pub channel: Option<u8>,
+    pub band: Option<Band>,
+    /// channel width and center segments from the HT, VHT and HE operation elements
+    pub operating_channel: Option<OperatingChannel>,
     pub vendor: Option<String>,
     pub password: Option<String>,
+    /// the AP beacons an empty or NUL-filled SSID, `ssid` is the revealed name if any
+    pub hidden: bool,
+    /// stations that exchanged data with this AP
+    pub clients: BTreeSet<[u8; 6]>,
+}
+
+impl AccessPoint {
+    /// Names of the capture interfaces that heard this access point, sorted.
+    pub fn interfaces(&self) -> Vec<&str> {
+        let mut interfaces: Vec<&str> = self
+            .observations
+            .iter()
+            .map(|o| o.interface.as_ref())
+            .collect();
+        interfaces.sort_unstable();
+        interfaces.dedup();
+        interfaces
+    }
+
+    /// Ways the AP breaks the rules of the country it advertises.
+    pub fn regulatory_violations(&self) -> Vec<RegulatoryViolation> {
+        self.regulatory.violations(self.channel, self.band)
+    }
+
+    /// Observations heard while tuned to another channel than the advertised one.
+    pub fn off_channel_observations(&self) -> usize {
+        self.observations.iter().filter(|o| o.off_channel).count()
+    }
+
+    /// A PMKID or enough of a 4-way handshake was captured to crack the passphrase.
+    pub fn handshake_captured(&self) -> bool {
+        self.handshake.as_ref().is_some_and(Handshake::is_crackable)
+    }
+
+    /// Authentication methods offered by this access point, empty when unknown.
+    pub fn auth_methods(&self) -> Vec<AuthMethod> {
+        auth_methods(self.security.as_ref(), &self.rsn.akms)
+    }
+
+    /// WPA2/WPA3-Enterprise, authenticating against a RADIUS server.
+    pub fn is_enterprise(&self) -> bool {
+        self.auth_methods().contains(&AuthMethod::Ieee8021x)
+    }
+
+    /// WPA2 or WPA3 network whose management frames can be forged, e.g. to deauthenticate
+    /// clients.
+    pub fn lacks_pmf(&self) -> bool {
+        matches!(
+            self.security,
+            Some(WifiSecurity::WPA2)
+                | Some(WifiSecurity::WPAWPA2)
+                | Some(WifiSecurity::WPA2WPA3)
+                | Some(WifiSecurity::WPA3)
+        ) && matches!(self.rsn.pmf, None | Some(Pmf::Disabled))
+    }
 }
 
 fn main() {
@@ -49,24 +146,93 @@ fn main() {
         .filter_module(env!("CARGO_CRATE_NAME"), args.log_level)
         .init();
 
-    if let Some(dir) = &args.directory {
-        let mut dir = dir.clone();
-        while dir.ends_with('/') {
-            dir.pop();
-        }
-        let mut working_dir = WORKING_DIR.lock().unwrap();
-        debug!("Set working directory to {}", dir);
-        *working_dir = dir;
+    if !args.directory.is_empty() {
+        let mut input_paths = INPUT_PATHS.lock().unwrap();
+        debug!("Set input paths to {:?}", args.directory);
+        *input_paths = args.directory.clone();
     }
 
-    let pos = get_positions();
+    debug!("Combining receive chains by {}", args.rssi_combine);
+
+    info!(
+        "Found {} capture, {} NMEA, {} gpsd, {} GPX and {} hashcat files",
+        input_files(InputKind::Capture).len(),
+        input_files(InputKind::Nmea).len(),
+        input_files(InputKind::Gpsd).len(),
+        input_files(InputKind::Gpx).len(),
+        input_files(InputKind::Hashcat).len()
+    );
+
+    let pos = get_positions(&FixFilter {
+        min_quality: args.min_fix_quality,
+        max_hdop: args.max_hdop,
+        min_satellites: args.min_satellites,
+    });
     info!("Found {} positions", pos.len());
 
-    let packets = get_packets();
-    info!("Found {} beacon packets", packets.len());
+    let mut captures = get_packets(args.verify_fcs, args.rssi_combine);
+    info!(
+        "Found {} beacon packets",
+        captures.iter().map(|c| c.packets.len()).sum::<usize>()
+    );
+
+    let max_gap = args.max_gap.unwrap_or(f64::INFINITY);
+    apply_time_offsets(
+        &mut captures,
+        &pos,
+        max_gap,
+        args.time_offset.unwrap_or(0.0),
+        &args.file_time_offset,
+        args.auto_time_offset,
+    );
 
-    let mut access_points = group_packets_by_mac(&packets, &pos);
+    let mut access_points = group_packets_by_mac(&captures, &pos, max_gap);
     info!("Found {} unique access points", access_points.len());
+    print_regulatory_summary(&access_points);
+    info!(
+        "Captured a crackable handshake for {} access points, a PMKID for {}",
+        access_points
+            .iter()
+            .filter(|ap| ap.handshake_captured())
+            .count(),
+        access_points
+            .iter()
+            .filter(|ap| ap.handshake.as_ref().is_some_and(|h| h.pmkid.is_some()))
+            .count()
+    );
+
+    let off_channel: usize = access_points
+        .iter()
+        .map(|ap| ap.off_channel_observations())
+        .sum();
+    if off_channel > 0 {
+        info!(
+            "{} observations were heard off the channel advertised by their AP",
+            off_channel
+        );
+    }
+    info!(
+        "{} access points are TKIP only, {} WPA2/WPA3 access points lack PMF",
+        access_points.iter().filter(|ap| ap.rsn.tkip_only()).count(),
+        access_points.iter().filter(|ap| ap.lacks_pmf()).count()
+    );
+
+    let revealed = reveal_hidden_ssids(&mut access_points);
+    if revealed > 0 {
+        info!("Revealed {} hidden SSIDs from hashcat ESSIDs", revealed);
+    }
+
+    let mut stations = group_packets_by_station(&captures, &pos, max_gap);
+    bind_vendors_to_stations(&mut stations);
+    bind_clients_to_aps(&mut access_points, &stations);
+    info!(
+        "Found {} client stations, {} access points have clients",
+        stations.len(),
+        access_points
+            .iter()
+            .filter(|ap| !ap.clients.is_empty())
+            .count()
+    );
 
     bind_vendors_to_aps(&mut access_points);
     let aps_with_vendors: usize = access_points
@@ -126,58 +292,160 @@ fn main() {
             &access_points,
             args.csv_output.as_deref().unwrap_or("wifi_aps.csv"),
         );
+        export_stations_to_csv(
+            &stations,
+            &args
+                .csv_output
+                .as_deref()
+                .map_or("wifi_stations.csv".to_string(), |name| {
+                    suffixed_filename(name, "stations")
+                }),
+        );
+        export_pnl_to_csv(
+            &stations,
+            &access_points,
+            &args
+                .csv_output
+                .as_deref()
+                .map_or("wifi_probes.csv".to_string(), |name| {
+                    suffixed_filename(name, "probes")
+                }),
+        );
     }
     if args.kml || args.kml_output.is_some() {
         export_to_kml(
             &access_points,
+            &stations,
             args.kml_output.as_deref().unwrap_or("wifi_aps.kml"),
         )
         .unwrap();
     }
 
-    if args.filter
-        && (args.csv || args.csv_output.is_some() || args.kml || args.kml_output.is_some())
+    // --filter, --band and --phy select the APs of the filtered outputs, the full outputs keep
+    // every AP
+    let selecting = args.filter || !args.band.is_empty() || !args.phy.is_empty();
+    if selecting && (args.csv || args.csv_output.is_some() || args.kml || args.kml_output.is_some())
     {
         let filtered_aps: Vec<AccessPoint> = access_points
             .into_iter()
-            .filter(|ap| match ap.security {
-                Some(WifiSecurity::Open) | Some(WifiSecurity::WEP) => true,
-                Some(WifiSecurity::WPA)
-                | Some(WifiSecurity::WPA2)
-                | Some(WifiSecurity::WPA3)
-                | Some(WifiSecurity::WPA2WPA3) => ap.password.is_some(),
-                Some(WifiSecurity::Unknown) => false,
-                None => false,
+            .filter(|ap| !args.filter || is_interesting(ap))
+            .filter(|ap| {
+                args.band.is_empty() || ap.band.is_some_and(|band| args.band.contains(&band))
+            })
+            .filter(|ap| {
+                args.phy.is_empty()
+                    || ap
+                        .phy
+                        .as_ref()
+                        .is_some_and(|phy| args.phy.contains(&phy.generation))
+            })
+            .collect();
+        info!(
+            "Kept {} access points in the filtered output",
+            filtered_aps.len()
+        );
+        // only the clients of the remaining APs
+        let filtered_stations: Vec<Station> = stations
+            .iter()
+            .filter(|station| {
+                filtered_aps
+                    .iter()
+                    .any(|ap| station.bssids.contains(&ap.mac))
             })
+            .cloned()
             .collect();
 
         if args.csv || args.csv_output.is_some() {
             let out_filename = match args.csv_output.as_deref() {
-                Some(name) => {
-                    let parts: Vec<&str> = name.rsplitn(2, '.').collect();
-                    if parts.len() == 2 {
-                        format!("{}_filtered.{}", parts[1], parts[0])
-                    } else {
-                        format!("{}_filtered", name)
-                    }
-                }
+                Some(name) => suffixed_filename(name, "filtered"),
                 None => "wifi_aps_filtered.csv".to_string(),
             };
             export_to_csv(&filtered_aps, &out_filename);
+            export_stations_to_csv(
+                &filtered_stations,
+                &args
+                    .csv_output
+                    .as_deref()
+                    .map_or("wifi_stations_filtered.csv".to_string(), |name| {
+                        suffixed_filename(name, "stations_filtered")
+                    }),
+            );
         }
         if args.kml || args.kml_output.is_some() {
             let out_filename = match args.kml_output.as_deref() {
-                Some(name) => {
-                    let parts: Vec<&str> = name.rsplitn(2, '.').collect();
-                    if parts.len() == 2 {
-                        format!("{}_filtered.{}", parts[1], parts[0])
-                    } else {
-                        format!("{}_filtered", name)
-                    }
-                }
+                Some(name) => suffixed_filename(name, "filtered"),
                 None => "wifi_aps_filtered.kml".to_string(),
             };
-            export_to_kml(&filtered_aps, &out_filename).unwrap();
+            export_to_kml(&filtered_aps, &filtered_stations, &out_filename).unwrap();
+        }
+    }
+}
+
+/// Open and WEP networks, and protected ones with a known password, enterprise or OWE
+/// authentication.
+fn is_interesting(ap: &AccessPoint) -> bool {
+    match ap.security {
+        Some(WifiSecurity::Open) | Some(WifiSecurity::WEP) => true,
+        Some(WifiSecurity::WPA)
+        | Some(WifiSecurity::WPA2)
+        | Some(WifiSecurity::WPAWPA2)
+        | Some(WifiSecurity::WPA3)
+        | Some(WifiSecurity::WPA2WPA3) => {
+            ap.password.is_some()
+                || ap.is_enterprise()
+                || ap.auth_methods().contains(&AuthMethod::Owe)
+        }
+        Some(WifiSecurity::Unknown) => false,
+        None => false,
+    }
+}
+
+/// Inserts `_suffix` before the extension: `out.csv` -> `out_suffix.csv`.
+fn suffixed_filename(name: &str, suffix: &str) -> String {
+    let parts: Vec<&str> = name.rsplitn(2, '.').collect();
+    if parts.len() == 2 {
+        format!("{}_{}.{}", parts[1], suffix, parts[0])
+    } else {
+        format!("{}_{}", name, suffix)
+    }
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    )
+}
+
+fn format_time(time: &Duration) -> String {
+    DateTime::from_timestamp(time.as_secs() as i64, time.subsec_nanos())
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn print_regulatory_summary(access_points: &[AccessPoint]) {
+    let mut countries: BTreeMap<&str, usize> = BTreeMap::new();
+    for ap in access_points {
+        if let Some(ref country) = ap.regulatory.country {
+            *countries.entry(country).or_insert(0) += 1;
+        }
+    }
+    if !countries.is_empty() {
+        let summary: Vec<String> = countries
+            .iter()
+            .map(|(country, count)| format!("{} ({} APs)", country, count))
+            .collect();
+        info!("Regulatory domains seen: {}", summary.join(", "));
+    }
+
+    for ap in access_points {
+        for violation in ap.regulatory_violations() {
+            warn!(
+                "AP {} breaks the rules of {}: {}",
+                format_mac(&ap.mac),
+                ap.regulatory.country.as_deref().unwrap_or("its country"),
+                violation
+            );
         }
     }
 }
@@ -216,7 +484,7 @@ fn export_to_csv(access_points: &[AccessPoint], filename: &str) {
 
     writeln!(
         file,
-        "MAC,SSID,Security,Latitude,Longitude,Observations,Method,MinRSSI,MaxRSSI,AvgRSSI"
+        "MAC,SSID,Security,Latitude,Longitude,Observations,Method,MinRSSI,MaxRSSI,AvgRSSI,Interfaces,Hidden,Clients,Auth,AKMs,GroupCipher,PairwiseCiphers,PMF,WPSState,WPSLocked,WPSManufacturer,WPSModel,WPSDeviceName,WPSSerial,WPSUUID,Band,Channel,Width,CenterChannels,OffChannel,Country,MaxTxPower,TxPower,RegulatoryViolations,PHY,SpatialStreams,SupportedWidths,AvgNoise,AvgSNR,ChainRSSI,HandshakeCaptured,HandshakeMessages,PMKID,HandshakeTime,HandshakeLatitude,HandshakeLongitude"
     )
     .unwrap();
 
@@ -224,14 +492,11 @@ fn export_to_csv(access_points: &[AccessPoint], filename: &str) {
         .iter()
         .filter(|ap| ap.estimated_position.is_some())
     {
-        let mac = format!(
-            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
-            ap.mac[0], ap.mac[1], ap.mac[2], ap.mac[3], ap.mac[4], ap.mac[5]
-        );
+        let mac = format_mac(&ap.mac);
         let ssid = ap
             .ssid
             .as_ref()
-            .map(|s| s.replace(",", ";"))
+            .map(|s| s.to_string().replace(",", ";"))
             .unwrap_or_else(|| "".to_string());
         let security = ap
             .security
@@ -244,10 +509,51 @@ fn export_to_csv(access_points: &[AccessPoint], filename: &str) {
             let min_rssi = signals.iter().min().unwrap_or(&0);
             let max_rssi = signals.iter().max().unwrap_or(&0);
             let avg_rssi = signals.iter().map(|&s| s as f64).sum::<f64>() / signals.len() as f64;
+            let interfaces = ap.interfaces().join(";").replace(",", ";");
+            let auth: Vec<String> = ap.auth_methods().iter().map(|m| m.to_string()).collect();
+            let akms: Vec<String> = ap.rsn.akms.iter().map(|a| a.to_string()).collect();
+            let pairwise: Vec<String> = ap
+                .rsn
+                .pairwise_ciphers
+                .iter()
+                .map(|c| c.to_string())
+                .collect();
+            let wps: Vec<String> = match ap.wps {
+                Some(ref wps) => vec![
+                    wps.state.map(|s| s.to_string()),
+                    wps.ap_setup_locked.map(|locked| locked.to_string()),
+                    wps.manufacturer.clone(),
+                    wps.model(),
+                    wps.device_name.clone(),
+                    wps.serial_number.clone(),
+                    wps.uuid_string(),
+                ]
+                .into_iter()
+                .map(|field| field.unwrap_or_default().replace(",", ";"))
+                .collect(),
+                None => vec![String::new(); 7],
+            };
+            let widths: Vec<String> = ap
+                .phy
+                .iter()
+                .flat_map(|phy| phy.widths.iter())
+                .map(|w| w.to_string())
+                .collect();
+            let violations: Vec<String> = ap
+                .regulatory_violations()
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            let chains: Vec<String> = average_chains(&ap.observations)
+                .iter()
+                .map(|(antenna, signal)| format!("{}:{:.1}", antenna, signal))
+                .collect();
+            let handshake_capture = ap.handshake.as_ref().and_then(|h| h.first_capture());
+            let handshake_position = handshake_capture.and_then(|c| c.position.as_ref());
 
             writeln!(
                 file,
-                "{},{},{},{:.6},{:.6},{},{},{},{},{:.1}",
+                "{},{},{},{:.6},{:.6},{},{},{},{},{:.1},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
                 mac,
                 ssid,
                 security,
@@ -259,7 +565,70 @@ fn export_to_csv(access_points: &[AccessPoint], filename: &str) {
                     .unwrap_or(&"unknown".to_string()),
                 min_rssi,
                 max_rssi,
-                avg_rssi
+                avg_rssi,
+                interfaces,
+                ap.hidden,
+                ap.clients.len(),
+                auth.join("+"),
+                akms.join(";"),
+                ap.rsn
+                    .group_cipher
+                    .map(|c| c.to_string())
+                    .unwrap_or_default(),
+                pairwise.join(";"),
+                ap.rsn.pmf.map(|p| p.to_string()).unwrap_or_default(),
+                wps.join(","),
+                ap.band.map(|b| b.to_string()).unwrap_or_default(),
+                ap.channel.map(|c| c.to_string()).unwrap_or_default(),
+                ap.operating_channel
+                    .map(|c| c.width.to_string())
+                    .unwrap_or_default(),
+                ap.operating_channel
+                    .and_then(|c| c.center_channels())
+                    .unwrap_or_default(),
+                ap.off_channel_observations(),
+                ap.regulatory.country_name().unwrap_or_default(),
+                ap.channel
+                    .and_then(|c| ap.regulatory.max_power(c))
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
+                ap.regulatory
+                    .tx_power
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
+                violations.join(";"),
+                ap.phy
+                    .as_ref()
+                    .map(|phy| phy.generation.to_string())
+                    .unwrap_or_default(),
+                ap.phy
+                    .as_ref()
+                    .and_then(|phy| phy.spatial_streams)
+                    .map(|n| n.to_string())
+                    .unwrap_or_default(),
+                widths.join(";"),
+                average_noise(&ap.observations)
+                    .map(|n| format!("{:.1}", n))
+                    .unwrap_or_default(),
+                average_snr(&ap.observations)
+                    .map(|snr| format!("{:.1}", snr))
+                    .unwrap_or_default(),
+                chains.join(";"),
+                ap.handshake_captured(),
+                ap.handshake
+                    .as_ref()
+                    .map(|h| h.messages_string())
+                    .unwrap_or_default(),
+                ap.handshake.as_ref().is_some_and(|h| h.pmkid.is_some()),
+                handshake_capture
+                    .map(|c| format_time(&c.timestamp))
+                    .unwrap_or_default(),
+                handshake_position
+                    .map(|p| format!("{:.6}", p.latitude))
+                    .unwrap_or_default(),
+                handshake_position
+                    .map(|p| format!("{:.6}", p.longitude))
+                    .unwrap_or_default()
             )
             .unwrap();
         }
@@ -267,3 +636,115 @@ fn export_to_csv(access_points: &[AccessPoint], filename: &str) {
 
     info!("Exported results to {}", filename);
 }
+
+fn export_stations_to_csv(stations: &[Station], filename: &str) {
+    let mut file = File::create(filename).unwrap();
+
+    writeln!(
+        file,
+        "MAC,Vendor,Randomized,FirstSeen,LastSeen,Latitude,Longitude,Observations,MinRSSI,MaxRSSI,BSSIDs"
+    )
+    .unwrap();
+
+    for station in stations {
+        // where the station was heard best, if anywhere along the track
+        let (latitude, longitude) = station
+            .strongest_observation()
+            .map(|o| {
+                (
+                    format!("{:.6}", o.position.latitude),
+                    format!("{:.6}", o.position.longitude),
+                )
+            })
+            .unwrap_or_default();
+        let signals = station.observations.iter().map(|o| o.signal_strength);
+        let bssids: Vec<String> = station.bssids.iter().map(format_mac).collect();
+
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            format_mac(&station.mac),
+            station.vendor.as_deref().unwrap_or("").replace(",", ";"),
+            station.randomized,
+            format_time(&station.first_seen),
+            format_time(&station.last_seen),
+            latitude,
+            longitude,
+            station.observations.len(),
+            signals
+                .clone()
+                .min()
+                .map_or(String::new(), |s| s.to_string()),
+            signals.max().map_or(String::new(), |s| s.to_string()),
+            bssids.join(";")
+        )
+        .unwrap();
+    }
+
+    info!("Exported {} stations to {}", stations.len(), filename);
+}
+
+/// Writes the preferred network list of every station, one line per probed SSID, with the
+/// located access points broadcasting that SSID.
+fn export_pnl_to_csv(stations: &[Station], access_points: &[AccessPoint], filename: &str) {
+    let mut file = File::create(filename).unwrap();
+
+    writeln!(
+        file,
+        "MAC,Vendor,Randomized,SSID,Probes,FirstSeen,LastSeen,Latitude,Longitude,LocatedAPs,NearestAP,NearestAPLatitude,NearestAPLongitude,NearestAPDistance"
+    )
+    .unwrap();
+
+    let mut lines = 0;
+    let mut located = 0;
+    for station in stations {
+        for (ssid, probes) in station.preferred_networks() {
+            // the last place the network was asked for
+            let position = probes.iter().rev().find_map(|p| p.position.as_ref());
+            let aps = locate_probed_network(ssid, position, access_points);
+            let (latitude, longitude) = position
+                .map(|p| (format!("{:.6}", p.latitude), format!("{:.6}", p.longitude)))
+                .unwrap_or_default();
+            let nearest = aps
+                .first()
+                .and_then(|(ap, distance)| {
+                    let ap_position = ap.estimated_position.as_ref()?;
+                    Some(format!(
+                        "{},{:.6},{:.6},{}",
+                        format_mac(&ap.mac),
+                        ap_position.latitude,
+                        ap_position.longitude,
+                        position.map_or(String::new(), |_| format!("{:.0}", distance))
+                    ))
+                })
+                .unwrap_or_else(|| ",,,".to_string());
+
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                format_mac(&station.mac),
+                station.vendor.as_deref().unwrap_or("").replace(",", ";"),
+                station.randomized,
+                ssid.to_string().replace(",", ";"),
+                probes.len(),
+                format_time(&probes[0].timestamp),
+                format_time(&probes[probes.len() - 1].timestamp),
+                latitude,
+                longitude,
+                aps.len(),
+                nearest
+            )
+            .unwrap();
+
+            lines += 1;
+            if !aps.is_empty() {
+                located += 1;
+            }
+        }
+    }
+
+    info!(
+        "Exported {} probed networks to {}, {} of them located",
+        lines, filename, located
+    );
+}
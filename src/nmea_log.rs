@@ -0,0 +1,233 @@
+use crate::geo::{FixQuality, Position, datetime_to_timestamp};
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime};
+use log::trace;
+use nmea::sentences::FixType;
+use nmea::sentences::rmc::RmcStatusOfFix;
+use nmea::{Error, ParseResult, parse_str};
+
+const KNOTS_TO_MPS: f64 = 0.514444;
+const HALF_DAY_SECS: i64 = 12 * 3600;
+
+/// State carried between the sentences of one NMEA log. Must not be shared between files.
+#[derive(Debug, Default)]
+pub struct NmeaState {
+    /// last date from RMC or ZDA, with the time of day it was sent at
+    date: Option<(NaiveDate, NaiveTime)>,
+    /// fix assembled from the sentences sharing the current fix time
+    epoch: Option<Epoch>,
+    /// sentences rejected because of a bad checksum
+    pub checksum_errors: usize,
+    /// fixes dropped because no RMC or ZDA gave the date yet
+    pub undated_fixes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Epoch {
+    time: NaiveTime,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    altitude: Option<f64>,
+    speed: Option<f64>,
+    track: Option<f64>,
+    hdop: Option<f64>,
+    satellites: Option<u32>,
+    fix_quality: Option<FixQuality>,
+}
+
+/// Parses one NMEA sentence. The talker ID (GP, GN, GL, ...) is ignored. Sentences with the same
+/// fix time are merged, and the fix is returned once a sentence with a newer fix time arrives,
+/// so call [`NmeaState::finish`] at the end of the file. A fix time without coordinates of its
+/// own yields nothing, the previous coordinates are never reused.
+pub fn parse_nmea_line(line: &str, state: &mut NmeaState) -> Option<Position> {
+    let sentence = match parse_str(line.trim()) {
+        Ok(sentence) => sentence,
+        Err(Error::ChecksumMismatch { calculated, found }) => {
+            trace!(
+                "Bad NMEA checksum {:02X}, expected {:02X}: {}",
+                found,
+                calculated,
+                line.trim()
+            );
+            state.checksum_errors += 1;
+            return None;
+        }
+        Err(_) => return None,
+    };
+
+    match sentence {
+        ParseResult::RMC(rmc) => {
+            let time = rmc.fix_time?;
+            if let Some(date) = rmc.fix_date {
+                state.date = Some((date, time));
+            }
+            let fix = state.start_epoch(time);
+
+            if rmc.status_of_fix != RmcStatusOfFix::Invalid
+                && let Some(epoch) = state.epoch.as_mut()
+            {
+                epoch.latitude = rmc.lat.or(epoch.latitude);
+                epoch.longitude = rmc.lon.or(epoch.longitude);
+                epoch.speed = rmc
+                    .speed_over_ground
+                    .map(|knots| knots as f64 * KNOTS_TO_MPS);
+                epoch.track = rmc.true_course.map(f64::from);
+                // GGA tells more about the fix, keep it if it came first
+                epoch.fix_quality = epoch.fix_quality.or(Some(
+                    if rmc.status_of_fix == RmcStatusOfFix::Differential {
+                        FixQuality::Dgps
+                    } else {
+                        FixQuality::Gps
+                    },
+                ));
+            }
+            fix
+        }
+        ParseResult::GGA(gga) => {
+            let fix = state.start_epoch(gga.fix_time?);
+
+            if let Some(fix_type) = gga.fix_type.filter(|t| *t != FixType::Invalid)
+                && let Some(epoch) = state.epoch.as_mut()
+            {
+                epoch.latitude = gga.latitude.or(epoch.latitude);
+                epoch.longitude = gga.longitude.or(epoch.longitude);
+                epoch.altitude = gga.altitude.map(f64::from);
+                epoch.hdop = gga.hdop.map(f64::from);
+                epoch.satellites = gga.fix_satellites;
+                epoch.fix_quality = Some(fix_type.into());
+            }
+            fix
+        }
+        ParseResult::GNS(gns) => {
+            let fix = state.start_epoch(gns.fix_time?);
+
+            let fix_type = FixType::from(gns.faa_modes);
+            if fix_type != FixType::Invalid
+                && let Some(epoch) = state.epoch.as_mut()
+            {
+                epoch.latitude = gns.lat.or(epoch.latitude);
+                epoch.longitude = gns.lon.or(epoch.longitude);
+                epoch.altitude = gns.alt.map(f64::from);
+                epoch.hdop = gns.hdop.map(f64::from);
+                epoch.satellites = Some(gns.nsattelites.into());
+                epoch.fix_quality = Some(fix_type.into());
+            }
+            fix
+        }
+        ParseResult::ZDA(zda) => {
+            if let (Some(time), Some(date)) = (zda.utc_time, zda.utc_date()) {
+                state.date = Some((date, time));
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+impl NmeaState {
+    /// Returns the fix still being assembled at the end of the file.
+    pub fn finish(&mut self) -> Option<Position> {
+        let epoch = self.epoch.take()?;
+        self.date_fix(epoch)
+    }
+
+    /// Starts a new epoch when `time` differs from the current one, returning the previous fix.
+    fn start_epoch(&mut self, time: NaiveTime) -> Option<Position> {
+        if self.epoch.as_ref().is_some_and(|epoch| epoch.time == time) {
+            return None;
+        }
+
+        let previous = self.epoch.replace(Epoch {
+            time,
+            ..Default::default()
+        })?;
+        self.date_fix(previous)
+    }
+
+    fn date_fix(&mut self, epoch: Epoch) -> Option<Position> {
+        let (latitude, longitude) = (epoch.latitude?, epoch.longitude?);
+        let Some((date, date_time)) = self.date else {
+            self.undated_fixes += 1;
+            return None;
+        };
+
+        // the date may have been sent on the other side of midnight
+        let date = match (epoch.time - date_time).num_seconds() {
+            secs if secs > HALF_DAY_SECS => date.checked_sub_days(Days::new(1))?,
+            secs if secs < -HALF_DAY_SECS => date.checked_add_days(Days::new(1))?,
+            _ => date,
+        };
+
+        Some(Position {
+            latitude,
+            longitude,
+            timestamp: datetime_to_timestamp(&NaiveDateTime::new(date, epoch.time)),
+            altitude: epoch.altitude,
+            speed: epoch.speed,
+            track: epoch.track,
+            hdop: epoch.hdop,
+            satellites: epoch.satellites,
+            fix_quality: epoch.fix_quality,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests_nmea_log {
+    use super::*;
+
+    #[test]
+    fn test_parse_nmea_line() {
+        let mut state = NmeaState::default();
+        let mut parse = |line| parse_nmea_line(line, &mut state);
+
+        // GGA before any RMC, the date comes from ZDA
+        assert!(parse("$GNZDA,120000.00,01,10,2026,00,00*7D").is_none());
+        assert!(
+            parse("$GNGGA,120000.00,4851.0000,N,00221.0000,E,2,08,0.9,35.0,M,47.0,M,,*4F")
+                .is_none()
+        );
+        assert!(
+            parse("$GPRMC,120000.00,A,4851.0000,N,00221.0000,E,10.0,90.0,011026,,,A*58").is_none()
+        );
+
+        // a new fix time flushes the merged GGA + RMC fix
+        let position = parse("$GNGGA,120001.00,,,,,0,00,99.9,,M,,M,,*43").unwrap();
+        assert_eq!(position.timestamp, 1_790_856_000.0);
+        assert_eq!(position.latitude, 48.85);
+        assert_eq!(position.altitude, Some(35.0));
+        assert_eq!(position.satellites, Some(8));
+        assert_eq!(position.fix_quality, Some(FixQuality::Dgps));
+        assert!(position.speed.is_some());
+
+        // the 12:00:01 fix had no coordinates, flushing it must not reuse the previous ones
+        assert!(
+            parse("$GNGGA,120002.00,4851.0010,N,00221.0010,E,1,08,0.9,35.0,M,47.0,M,,*4E")
+                .is_none()
+        );
+        let position = state.finish().unwrap();
+        assert_eq!(position.timestamp, 1_790_856_002.0);
+        assert!((position.latitude - 48.850017).abs() < 1e-6);
+
+        let mut parse = |line| parse_nmea_line(line, &mut state);
+        assert!(
+            parse("$GNGGA,120003.00,4851.0010,N,00221.0010,E,1,08,0.9,35.0,M,47.0,M,,*00")
+                .is_none()
+        );
+        assert_eq!(state.finish().map(|p| p.timestamp), None);
+        assert_eq!(state.checksum_errors, 1);
+    }
+
+    #[test]
+    fn test_midnight_rollover() {
+        let mut state = NmeaState::default();
+        let mut parse = |line| parse_nmea_line(line, &mut state);
+
+        parse("$GNZDA,120000.00,01,10,2026,00,00*7D");
+        parse("$GNGGA,235959.50,4851.0000,N,00221.0000,E,1,08,0.9,35.0,M,47.0,M,,*4B");
+        parse("$GNZDA,000000.00,02,10,2026,00,00*7D");
+        let before = parse("$GNGGA,000000.50,4851.0000,N,00221.0000,E,1,08,0.9,35.0,M,47.0,M,,*4A");
+        assert_eq!(before.unwrap().timestamp, 1_790_899_199.5);
+        assert_eq!(state.finish().unwrap().timestamp, 1_790_899_200.5);
+    }
+}
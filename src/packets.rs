@@ -1,86 +1,267 @@
-use crate::geo::{Position, get_position_at, rssi_to_distance};
-use crate::{AccessPoint, Observation, WORKING_DIR};
-use log::{debug, error, trace};
-use pcap_file::pcapng::PcapNgReader;
+use crate::channel::{
+    Band, OperatingChannel, find_ds_channel, frequency_to_channel, parse_operation_elements,
+};
+use crate::clock::parse_embedded_nmea;
+use crate::eapol::{EapolKey, Handshake, mic_len, parse_eapol_key};
+use crate::geo::{Position, PositionMiss, get_position_at, rssi_to_distance};
+use crate::input::{InputKind, input_files, open_input};
+use crate::phy::{PhyCapabilities, parse_phy_capabilities};
+use crate::regulatory::{RegulatoryInfo, parse_regulatory_elements};
+use crate::security::{RsnInfo, WifiSecurity, parse_wifi_security};
+use crate::signal::{ChainSignal, RadiotapSignal, RssiCombine};
+use crate::ssid::Ssid;
+use crate::wps::{WpsInfo, parse_wps_element};
+use crate::{AccessPoint, Observation};
+use log::{debug, error, info, trace, warn};
+use pcap_file::DataLink;
+use pcap_file::pcap::PcapReader;
+use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketOption;
+use pcap_file::pcapng::blocks::interface_description::{
+    InterfaceDescriptionBlock, InterfaceDescriptionOption,
+};
+use pcap_file::pcapng::{Block, PcapNgReader};
 use radiotap::Radiotap;
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Packet {
     pub timestamp: Duration,
     pub source_address: Option<[u8; 6]>,
-    pub ssid: Option<String>,
+    pub ssid: Option<Ssid>,
+    /// BSSID a management frame belongs to, also set for frames sent by clients
+    pub bssid: Option<[u8; 6]>,
+    /// beacon with a zero-length or NUL-filled SSID
+    pub hidden_ssid: bool,
+    /// client station of a data frame
+    pub station: Option<[u8; 6]>,
+    /// the frame was sent by `station`, so the signal strength is the station's
+    pub station_transmitted: bool,
+    /// probe request from `station`, `ssid` is the probed network unless it's a wildcard probe
+    pub probe_request: bool,
+    /// signal of the frame, the chains combined according to `--rssi-combine`
     pub signal_strength: Option<i8>,
+    /// per-chain signals, for adapters reporting more than one receive chain
+    pub chain_signals: Vec<ChainSignal>,
+    /// noise floor in dBm
+    pub noise: Option<i8>,
+    /// channel the adapter was tuned to, from the radiotap frequency
     pub channel: Option<u8>,
+    /// channel the AP advertises in its DS Parameter Set or HT/HE operation element
+    pub advertised_channel: Option<u8>,
+    /// band of the frequency the packet was captured on
+    pub band: Option<Band>,
+    /// channel width announced by a beacon or probe response
+    pub operating_channel: Option<OperatingChannel>,
     pub security: Option<WifiSecurity>,
+    /// cipher and AKM suites advertised by a beacon or probe response
+    pub rsn: RsnInfo,
+    /// WPS element of a beacon or probe response
+    pub wps: Option<WpsInfo>,
+    /// Country, Power Constraint and TPC Report elements of a beacon or probe response
+    pub regulatory: Option<RegulatoryInfo>,
+    /// PHY generation, spatial streams and channel widths of a beacon or probe response
+    pub phy: Option<PhyCapabilities>,
+    /// EAPOL-Key frame of a 4-way handshake between `bssid` and `station`
+    pub eapol: Option<EapolKey>,
+    pub interface: Arc<str>,
+}
+
+/// Packets read from one capture file.
+#[derive(Debug)]
+pub struct Capture {
+    pub path: PathBuf,
+    pub packets: Vec<Packet>,
+    /// (capture timestamp, GPS time in seconds) pairs from NMEA sentences that the capture tool
+    /// embedded in packet comments, e.g. hcxdumptool `--nmea_pcapng`
+    pub gps_samples: Vec<(Duration, f64)>,
+    /// frames dropped because they failed the FCS check
+    pub bad_fcs_frames: usize,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum WifiSecurity {
-    Open,
-    WEP,
-    WPA,
-    WPA2,
-    WPA3,
-    WPA2WPA3,
-    Unknown,
+/// Packets of a capture file that could not be positioned, by reason.
+#[derive(Debug, Default)]
+struct DroppedPackets {
+    no_fix: usize,
+    gap: usize,
+    out_of_range: usize,
 }
 
-impl std::fmt::Display for WifiSecurity {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            WifiSecurity::Open => write!(f, "Open"),
-            WifiSecurity::WEP => write!(f, "WEP"),
-            WifiSecurity::WPA => write!(f, "WPA"),
-            WifiSecurity::WPA2 => write!(f, "WPA2"),
-            WifiSecurity::WPA3 => write!(f, "WPA3"),
-            WifiSecurity::WPA2WPA3 => write!(f, "WPA2/WPA3"),
-            WifiSecurity::Unknown => write!(f, "Unknown"),
+impl DroppedPackets {
+    fn count(&mut self, miss: PositionMiss) {
+        match miss {
+            PositionMiss::NoFix => self.no_fix += 1,
+            PositionMiss::Gap => self.gap += 1,
+            PositionMiss::OutOfRange => self.out_of_range += 1,
         }
     }
+
+    fn total(&self) -> usize {
+        self.no_fix + self.gap + self.out_of_range
+    }
 }
 
-pub fn group_packets_by_mac(packets: &[Packet], positions: &[Position]) -> Vec<AccessPoint> {
+pub fn group_packets_by_mac(
+    captures: &[Capture],
+    positions: &[Position],
+    max_gap: f64,
+) -> Vec<AccessPoint> {
     let mut mac_map: HashMap<[u8; 6], AccessPoint> = HashMap::new();
+    // SSIDs named in any frame for a BSSID, positioned or not, to decloak hidden networks
+    let mut named_ssids: HashMap<[u8; 6], Ssid> = HashMap::new();
+    let mut hidden_bssids: HashSet<[u8; 6]> = HashSet::new();
 
-    for packet in packets {
-        if let Some(mac) = packet.source_address {
-            if let Some(signal) = packet.signal_strength {
-                if let Some(pos) = get_position_at(&packet.timestamp, positions) {
-                    let distance = rssi_to_distance(signal);
+    for packet in captures.iter().flat_map(|c| c.packets.iter()) {
+        if let Some(bssid) = packet.bssid {
+            if packet.hidden_ssid {
+                hidden_bssids.insert(bssid);
+            } else if let Some(ref ssid) = packet.ssid {
+                named_ssids.entry(bssid).or_insert_with(|| ssid.clone());
+            }
+        }
+    }
 
-                    let observation = Observation {
-                        position: pos,
-                        signal_strength: signal,
-                        distance,
-                    };
+    for capture in captures {
+        let mut dropped = DroppedPackets::default();
 
-                    let ap = mac_map.entry(mac).or_insert_with(|| AccessPoint {
-                        mac,
-                        ssid: packet.ssid.clone(),
-                        observations: Vec::new(),
-                        estimated_position: None,
-                        position_method: None,
-                        security: None,
-                        channel: packet.channel,
-                        vendor: None,
-                        password: None,
-                    });
-
-                    ap.observations.push(observation);
-
-                    if ap.ssid.is_none() && packet.ssid.is_some() {
-                        ap.ssid = packet.ssid.clone();
-                    }
+        for packet in &capture.packets {
+            let (Some(mac), Some(signal)) = (packet.source_address, packet.signal_strength) else {
+                continue;
+            };
+            let pos = match get_position_at(&packet.timestamp, positions, max_gap) {
+                Ok(pos) => pos,
+                Err(miss) => {
+                    dropped.count(miss);
+                    continue;
+                }
+            };
 
-                    if ap.security.is_none() && packet.security.is_some() {
-                        ap.security = packet.security.clone();
-                    }
+            let distance = rssi_to_distance(signal);
+
+            let observation = Observation {
+                position: pos,
+                signal_strength: signal,
+                distance,
+                interface: packet.interface.clone(),
+                chains: packet.chain_signals.clone(),
+                noise: packet.noise,
+                off_channel: packet
+                    .advertised_channel
+                    .zip(packet.channel)
+                    .is_some_and(|(advertised, tuned)| advertised != tuned),
+            };
+
+            let ap = mac_map.entry(mac).or_insert_with(|| AccessPoint {
+                mac,
+                ssid: packet.ssid.clone(),
+                observations: Vec::new(),
+                estimated_position: None,
+                position_method: None,
+                security: None,
+                rsn: RsnInfo::default(),
+                wps: None,
+                regulatory: RegulatoryInfo::default(),
+                phy: None,
+                handshake: None,
+                channel: None,
+                band: packet.band,
+                operating_channel: None,
+                vendor: None,
+                password: None,
+                hidden: false,
+                clients: BTreeSet::new(),
+            });
+
+            ap.observations.push(observation);
+
+            if ap.ssid.is_none() && packet.ssid.is_some() {
+                ap.ssid = packet.ssid.clone();
+            }
+
+            if ap.security.is_none() && packet.security.is_some() {
+                ap.security = packet.security.clone();
+            }
+
+            if ap.rsn == RsnInfo::default() && packet.rsn != RsnInfo::default() {
+                ap.rsn = packet.rsn.clone();
+            }
+
+            // the advertised channel beats the tuned one, which may be an adjacent channel
+            if let Some(channel) = packet.advertised_channel {
+                ap.channel = Some(channel);
+            } else if ap.channel.is_none() {
+                ap.channel = packet.channel;
+            }
+
+            if ap.operating_channel.is_none() {
+                ap.operating_channel = packet.operating_channel;
+            }
+
+            if let Some(ref wps) = packet.wps {
+                ap.wps.get_or_insert_with(WpsInfo::default).merge(wps);
+            }
+
+            if let Some(ref regulatory) = packet.regulatory {
+                ap.regulatory.merge(regulatory);
+            }
+
+            if let Some(ref phy) = packet.phy {
+                match ap.phy {
+                    Some(ref mut ap_phy) => ap_phy.merge(phy),
+                    None => ap.phy = Some(phy.clone()),
                 }
             }
         }
+
+        if dropped.total() > 0 {
+            warn!(
+                "Dropped {} of {} packets from {:?}: {} without GPS fix, {} in GPS gaps, {} outside the GPS track",
+                dropped.total(),
+                capture.packets.len(),
+                capture.path,
+                dropped.no_fix,
+                dropped.gap,
+                dropped.out_of_range
+            );
+        }
+    }
+
+    // handshake frames from clients don't observe the AP, so they are bound by BSSID
+    for packet in captures.iter().flat_map(|c| c.packets.iter()) {
+        if let (Some(key), Some(bssid)) = (&packet.eapol, packet.bssid)
+            && let Some(ap) = mac_map.get_mut(&bssid)
+        {
+            let position = get_position_at(&packet.timestamp, positions, max_gap).ok();
+            let mic_len = mic_len(&ap.rsn.akms);
+            ap.handshake.get_or_insert_with(Handshake::default).add(
+                key,
+                mic_len,
+                packet.timestamp,
+                position,
+            );
+        }
+    }
+
+    for ap in mac_map.values_mut() {
+        ap.hidden = hidden_bssids.contains(&ap.mac);
+        if ap.ssid.is_none() {
+            ap.ssid = named_ssids.get(&ap.mac).cloned();
+        }
+    }
+
+    let hidden = mac_map.values().filter(|ap| ap.hidden).count();
+    if hidden > 0 {
+        info!(
+            "Found {} hidden SSIDs, revealed {} from probe responses and association requests",
+            hidden,
+            mac_map
+                .values()
+                .filter(|ap| ap.hidden && ap.ssid.is_some())
+                .count()
+        );
     }
 
     mac_map.into_values().collect()
@@ -96,13 +277,10 @@ fn extract_mac(bytes: &[u8]) -> [u8; 6] {
     mac
 }
 
-fn parse_management_frame_body(frame_body: &[u8]) -> Option<String> {
-    if frame_body.len() < 12 {
-        return None;
-    }
-
-    let mut ssid = None;
-    let mut offset = 12; // skip fixed parameters
+/// Returns the content of the SSID element of a management frame body, whose fixed parameters
+/// are `fixed_len` bytes long.
+fn find_ssid_element(frame_body: &[u8], fixed_len: usize) -> Option<&[u8]> {
+    let mut offset = fixed_len; // skip fixed parameters
 
     while offset + 2 <= frame_body.len() {
         let tag_number = frame_body[offset];
@@ -113,111 +291,447 @@ fn parse_management_frame_body(frame_body: &[u8]) -> Option<String> {
         }
 
         // SSID element (tag 0)
-        if tag_number == 0 && tag_length > 0 {
-            let ssid_bytes = &frame_body[offset + 2..offset + 2 + tag_length];
-            if let Ok(ssid_str) = std::str::from_utf8(ssid_bytes) {
-                if !ssid_str.is_empty() {
-                    ssid = Some(ssid_str.to_string());
-                }
-            }
-            break;
+        if tag_number == 0 {
+            return Some(&frame_body[offset + 2..offset + 2 + tag_length]);
         }
 
         offset += 2 + tag_length;
     }
 
-    ssid
+    None
+}
+
+/// Hidden networks beacon an empty SSID, or one with every byte replaced by NUL.
+fn is_hidden_ssid(ssid: &[u8]) -> bool {
+    ssid.iter().all(|&b| b == 0)
+}
+
+fn parse_management_frame_body(frame_body: &[u8], fixed_len: usize) -> Option<Ssid> {
+    let ssid_bytes = find_ssid_element(frame_body, fixed_len)?;
+    if is_hidden_ssid(ssid_bytes) {
+        return None;
+    }
+
+    Some(Ssid::new(ssid_bytes))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaptureFormat {
+    Pcap,
+    PcapNg,
+}
+
+/// Detects the capture format from the first bytes of a file, regardless of its extension.
+fn detect_capture_format(magic: &[u8]) -> Option<CaptureFormat> {
+    if magic.len() < 4 {
+        return None;
+    }
+
+    match [magic[0], magic[1], magic[2], magic[3]] {
+        // section header block type
+        [0x0a, 0x0d, 0x0d, 0x0a] => Some(CaptureFormat::PcapNg),
+        // microsecond and nanosecond pcap magics, both byte orders
+        [0xd4, 0xc3, 0xb2, 0xa1]
+        | [0xa1, 0xb2, 0xc3, 0xd4]
+        | [0x4d, 0x3c, 0xb2, 0xa1]
+        | [0xa1, 0xb2, 0x3c, 0x4d] => Some(CaptureFormat::Pcap),
+        _ => None,
+    }
 }
 
-pub fn get_packets() -> Vec<Packet> {
-    let mut all_packets = Vec::new();
+/// Reads every capture file. With `verify_fcs`, frames whose FCS doesn't match are dropped. The
+/// receive chains of each frame are combined into its RSSI by `rssi_combine`.
+pub fn get_packets(verify_fcs: bool, rssi_combine: RssiCombine) -> Vec<Capture> {
+    let mut captures = Vec::new();
+
+    for path in input_files(InputKind::Capture) {
+        let mut reader = match open_input(&path) {
+            Ok(reader) => reader,
+            Err(e) => {
+                error!("Failed to open capture file {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let format = match reader.fill_buf() {
+            Ok(magic) => detect_capture_format(magic),
+            Err(e) => {
+                error!("Failed to read capture file {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        let mut capture = Capture {
+            path: path.clone(),
+            packets: Vec::new(),
+            gps_samples: Vec::new(),
+            bad_fcs_frames: 0,
+        };
 
-    let paths = std::fs::read_dir(WORKING_DIR.lock().unwrap().as_str()).unwrap();
-    for path in paths {
-        let path = path.unwrap().path();
-        if let Some(ext) = path.extension() {
-            if ext == "pcapng" {
+        match format {
+            Some(CaptureFormat::PcapNg) => {
                 debug!("Reading pcapng file: {:?}", path);
+                read_pcapng(reader, &mut capture, verify_fcs, rssi_combine);
+            }
+            Some(CaptureFormat::Pcap) => {
+                debug!("Reading pcap file: {:?}", path);
+                read_pcap(reader, &mut capture, verify_fcs, rssi_combine);
+            }
+            None => {
+                warn!("Skipping {:?}: not a pcap or pcapng capture", path);
+                continue;
+            }
+        }
 
-                let file = File::open(&path).unwrap();
-                let mut pcapng_reader = PcapNgReader::new(file).unwrap();
+        if capture.bad_fcs_frames > 0 {
+            warn!(
+                "Rejected {} corrupted frames (bad FCS) from {:?}",
+                capture.bad_fcs_frames, path
+            );
+        }
 
-                while let Some(block) = pcapng_reader.next_block() {
-                    match block {
-                        Ok(block) => {
-                            if let pcap_file::pcapng::Block::EnhancedPacket(epb) = block {
-                                let data = epb.data.as_ref();
+        captures.push(capture);
+    }
 
-                                if let Some(packet) = parse_wifi_packet(data, epb.timestamp) {
-                                    all_packets.push(packet);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Error reading block: {:?}", e);
-                            break;
+    captures
+}
+
+/// A capture interface, as described by a pcapng Interface Description Block or a pcap header.
+#[derive(Debug, Clone)]
+struct CaptureInterface {
+    name: Arc<str>,
+    linktype: DataLink,
+    snaplen: u32,
+    ts_resolution: u8,
+    /// seconds added to every timestamp, signed per the pcapng spec
+    ts_offset: i64,
+    /// bytes of FCS at the end of each frame, when radiotap doesn't tell
+    fcs_len: u8,
+}
+
+impl CaptureInterface {
+    fn from_idb(idb: &InterfaceDescriptionBlock, id: usize, path: &Path) -> CaptureInterface {
+        let mut name = None;
+        let mut description = None;
+        // pcapng default resolution is microseconds
+        let mut ts_resolution = 6;
+        let mut ts_offset = 0;
+        let mut fcs_len = 0;
+
+        for option in &idb.options {
+            match option {
+                InterfaceDescriptionOption::IfName(n) => name = Some(n.to_string()),
+                InterfaceDescriptionOption::IfDescription(d) => description = Some(d.to_string()),
+                InterfaceDescriptionOption::IfTsResol(r) => ts_resolution = *r,
+                InterfaceDescriptionOption::IfTsOffset(o) => ts_offset = *o as i64,
+                InterfaceDescriptionOption::IfFcsLen(l) => fcs_len = *l,
+                _ => {}
+            }
+        }
+
+        let name = name.or(description).unwrap_or_else(|| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            format!("{}#{}", file_name, id)
+        });
+
+        CaptureInterface {
+            name: Arc::from(name),
+            linktype: idb.linktype,
+            snaplen: idb.snaplen,
+            ts_resolution,
+            ts_offset,
+            fcs_len,
+        }
+    }
+
+    /// Converts a raw pcapng timestamp, expressed in `if_tsresol` units, to a duration since epoch.
+    fn timestamp(&self, raw: u64) -> Duration {
+        let exponent = (self.ts_resolution & 0x7f) as u32;
+        let timestamp = if self.ts_resolution & 0x80 == 0 {
+            // negative power of 10
+            if exponent <= 9 {
+                Duration::from_nanos(raw.saturating_mul(10u64.pow(9 - exponent)))
+            } else {
+                Duration::from_nanos(raw / 10u64.saturating_pow(exponent - 9))
+            }
+        } else {
+            // negative power of 2
+            let exponent = exponent.min(63);
+            let secs = raw >> exponent;
+            let fraction = (raw & ((1u64 << exponent) - 1)) as u128;
+            let nanos = (fraction * 1_000_000_000) >> exponent;
+            Duration::new(secs, nanos as u32)
+        };
+
+        let offset = Duration::from_secs(self.ts_offset.unsigned_abs());
+        if self.ts_offset >= 0 {
+            timestamp.checked_add(offset).unwrap_or(Duration::MAX)
+        } else {
+            timestamp.saturating_sub(offset)
+        }
+    }
+}
+
+fn read_pcapng<R: Read>(
+    reader: R,
+    capture: &mut Capture,
+    verify_fcs: bool,
+    rssi_combine: RssiCombine,
+) {
+    let path = capture.path.clone();
+    let mut pcapng_reader = match PcapNgReader::new(reader) {
+        Ok(reader) => reader,
+        Err(e) => {
+            error!("Error reading pcapng header: {:?}", e);
+            return;
+        }
+    };
+
+    let mut interfaces: Vec<CaptureInterface> = Vec::new();
+    // simple packet blocks carry no timestamp, they inherit the last one seen
+    let mut last_timestamp = Duration::ZERO;
+
+    while let Some(block) = pcapng_reader.next_block() {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                error!("Error reading block: {:?}", e);
+                break;
+            }
+        };
+
+        match block {
+            // interface ids are scoped to their section
+            Block::SectionHeader(_) => interfaces.clear(),
+            Block::InterfaceDescription(idb) => {
+                let interface = CaptureInterface::from_idb(&idb, interfaces.len(), &path);
+                debug!(
+                    "Interface {} ({}): link type {:?}, timestamp resolution {:#04x}",
+                    interfaces.len(),
+                    interface.name,
+                    interface.linktype,
+                    interface.ts_resolution
+                );
+                if !is_supported_linktype(interface.linktype) {
+                    warn!(
+                        "Interface {} in {:?} has unsupported link type {:?}, its frames are skipped",
+                        interface.name, path, interface.linktype
+                    );
+                }
+                interfaces.push(interface);
+            }
+            Block::EnhancedPacket(epb) => {
+                let Some(interface) = interfaces.get(epb.interface_id as usize) else {
+                    warn!("Packet references unknown interface {}", epb.interface_id);
+                    continue;
+                };
+                // pcap_file stores the raw timestamp units as nanoseconds
+                let timestamp = interface.timestamp(epb.timestamp.as_nanos() as u64);
+                last_timestamp = timestamp;
+
+                let mut crc_error = false;
+                for option in &epb.options {
+                    let text = match option {
+                        // link-layer dependent error bits, bit 24 is a CRC error
+                        EnhancedPacketOption::Flags(flags) => {
+                            crc_error = flags & (1 << 24) != 0;
+                            continue;
                         }
+                        EnhancedPacketOption::Comment(comment) => comment.as_ref(),
+                        EnhancedPacketOption::CustomUtf8(custom) => custom.value.as_ref(),
+                        _ => continue,
+                    };
+                    if let Some(gps_time) = parse_embedded_nmea(text, timestamp) {
+                        capture.gps_samples.push((timestamp, gps_time));
                     }
                 }
+
+                if let Some(packet) = parse_wifi_packet(
+                    epb.data.as_ref(),
+                    interface,
+                    timestamp,
+                    crc_error,
+                    verify_fcs,
+                    rssi_combine,
+                    &mut capture.bad_fcs_frames,
+                ) {
+                    capture.packets.push(packet);
+                }
+            }
+            Block::Packet(pb) => {
+                let Some(interface) = interfaces.get(pb.interface_id as usize) else {
+                    warn!("Packet references unknown interface {}", pb.interface_id);
+                    continue;
+                };
+                let timestamp = interface.timestamp(pb.timestamp);
+                last_timestamp = timestamp;
+
+                if let Some(packet) = parse_wifi_packet(
+                    pb.data.as_ref(),
+                    interface,
+                    timestamp,
+                    false,
+                    verify_fcs,
+                    rssi_combine,
+                    &mut capture.bad_fcs_frames,
+                ) {
+                    capture.packets.push(packet);
+                }
+            }
+            Block::SimplePacket(spb) => {
+                let Some(interface) = interfaces.first() else {
+                    warn!("Simple packet block found before any interface description");
+                    continue;
+                };
+                // the block data includes padding, the real length is bounded by snaplen
+                let mut length = (spb.original_len as usize).min(spb.data.len());
+                if interface.snaplen > 0 {
+                    length = length.min(interface.snaplen as usize);
+                }
+
+                if let Some(packet) = parse_wifi_packet(
+                    &spb.data[..length],
+                    interface,
+                    last_timestamp,
+                    false,
+                    verify_fcs,
+                    rssi_combine,
+                    &mut capture.bad_fcs_frames,
+                ) {
+                    capture.packets.push(packet);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_pcap<R: Read>(
+    reader: R,
+    capture: &mut Capture,
+    verify_fcs: bool,
+    rssi_combine: RssiCombine,
+) {
+    let path = &capture.path;
+    let mut pcap_reader = match PcapReader::new(reader) {
+        Ok(reader) => reader,
+        Err(e) => {
+            error!("Error reading pcap header: {:?}", e);
+            return;
+        }
+    };
+
+    let header = pcap_reader.header();
+    if !is_supported_linktype(header.datalink) {
+        warn!(
+            "Skipping {:?}: unsupported link type {:?}, only radiotap (127) and raw 802.11 (105) are handled",
+            path, header.datalink
+        );
+        return;
+    }
+
+    let interface = CaptureInterface {
+        name: Arc::from(path.file_name().unwrap_or_default().to_string_lossy()),
+        linktype: header.datalink,
+        snaplen: header.snaplen,
+        ts_resolution: 9,
+        ts_offset: 0,
+        fcs_len: 0,
+    };
+
+    while let Some(pcap_packet) = pcap_reader.next_packet() {
+        match pcap_packet {
+            Ok(pcap_packet) => {
+                let data = pcap_packet.data.as_ref();
+
+                if let Some(packet) = parse_wifi_packet(
+                    data,
+                    &interface,
+                    pcap_packet.timestamp,
+                    false,
+                    verify_fcs,
+                    rssi_combine,
+                    &mut capture.bad_fcs_frames,
+                ) {
+                    capture.packets.push(packet);
+                }
+            }
+            Err(e) => {
+                error!("Error reading packet: {:?}", e);
+                break;
             }
         }
     }
+}
 
-    all_packets
+fn is_supported_linktype(linktype: DataLink) -> bool {
+    matches!(
+        linktype,
+        DataLink::IEEE802_11_RADIOTAP | DataLink::IEEE802_11
+    )
 }
 
-fn parse_wifi_packet(data: &[u8], timestamp: Duration) -> Option<Packet> {
-    let radiotap = Radiotap::from_bytes(data).ok()?;
-    let radiotap_len = radiotap.header.length as usize;
+/// Parses a captured frame. `crc_error` is the capture's own bad-FCS flag (pcapng EPB flags),
+/// frames rejected for a bad FCS are counted in `bad_fcs_frames`.
+fn parse_wifi_packet(
+    data: &[u8],
+    interface: &CaptureInterface,
+    timestamp: Duration,
+    crc_error: bool,
+    verify_fcs: bool,
+    rssi_combine: RssiCombine,
+    bad_fcs_frames: &mut usize,
+) -> Option<Packet> {
+    let mut signal = RadiotapSignal::default();
+    let mut channel = None;
+    let mut band = None;
+    let mut has_fcs = interface.fcs_len == 4;
+    let mut bad_fcs = crc_error;
+
+    let wlan_data = match interface.linktype {
+        DataLink::IEEE802_11_RADIOTAP => {
+            let radiotap = Radiotap::from_bytes(data).ok()?;
+            let radiotap_len = radiotap.header.length;
+
+            if data.len() < radiotap_len + 24 {
+                return None;
+            }
+
+            signal = RadiotapSignal::from_radiotap(data);
+            if let Some(flags) = radiotap.flags {
+                has_fcs = flags.fcs;
+                bad_fcs |= flags.bad_fcs;
+            }
+            if let Some((c_band, c_channel)) =
+                radiotap.channel.and_then(|c| frequency_to_channel(c.freq))
+            {
+                band = Some(c_band);
+                channel = Some(c_channel);
+            }
+
+            &data[radiotap_len..]
+        }
+        DataLink::IEEE802_11 => data,
+        _ => return None,
+    };
 
-    if data.len() < radiotap_len + 24 {
+    // corrupted frames make up SSIDs and BSSIDs with flipped bits
+    if bad_fcs {
+        trace!("Dropping frame flagged with a bad FCS");
+        *bad_fcs_frames += 1;
         return None;
     }
+    let wlan_data = if has_fcs {
+        let (frame, fcs) = wlan_data.split_at_checked(wlan_data.len().checked_sub(4)?)?;
+        if verify_fcs && !fcs_matches(frame, fcs) {
+            trace!("Dropping frame whose FCS doesn't match its content");
+            *bad_fcs_frames += 1;
+            return None;
+        }
+        frame
+    } else {
+        wlan_data
+    };
 
-    let signal_strength = radiotap.antenna_signal.map(|s| s.value);
-    let channel = radiotap.channel.and_then(|c| match c.freq {
-        2412 => Some(1),
-        2417 => Some(2),
-        2422 => Some(3),
-        2427 => Some(4),
-        2432 => Some(5),
-        2437 => Some(6),
-        2442 => Some(7),
-        2447 => Some(8),
-        2452 => Some(9),
-        2457 => Some(10),
-        2462 => Some(11),
-        2467 => Some(12),
-        2472 => Some(13),
-        2484 => Some(14),
-        5180 => Some(36),
-        5200 => Some(40),
-        5220 => Some(44),
-        5240 => Some(48),
-        5260 => Some(52),
-        5280 => Some(56),
-        5300 => Some(60),
-        5320 => Some(64),
-        5500 => Some(100),
-        5520 => Some(104),
-        5540 => Some(108),
-        5560 => Some(112),
-        5580 => Some(116),
-        5600 => Some(120),
-        5620 => Some(124),
-        5640 => Some(128),
-        5660 => Some(132),
-        5680 => Some(136),
-        5700 => Some(140),
-        5745 => Some(149),
-        5765 => Some(153),
-        5785 => Some(157),
-        5805 => Some(161),
-        5825 => Some(165),
-        _ => None,
-    }); // I want to die
-
-    let wlan_data = &data[radiotap_len..];
     if wlan_data.len() < 24 {
         return None;
     }
@@ -229,8 +743,20 @@ fn parse_wifi_packet(data: &[u8], timestamp: Duration) -> Option<Packet> {
     let from_ds = (frame_control >> 9) & 0x01;
 
     let ap_mac: Option<[u8; 6]>;
-    let mut ssid: Option<String> = None;
+    let mut bssid: Option<[u8; 6]> = None;
+    let mut ssid: Option<Ssid> = None;
+    let mut hidden_ssid = false;
+    let mut station: Option<[u8; 6]> = None;
+    let mut station_transmitted = false;
+    let mut probe_request = false;
     let mut security: Option<WifiSecurity> = None;
+    let mut rsn = RsnInfo::default();
+    let mut wps: Option<WpsInfo> = None;
+    let mut operating_channel: Option<OperatingChannel> = None;
+    let mut advertised_channel: Option<u8> = None;
+    let mut regulatory: Option<RegulatoryInfo> = None;
+    let mut phy: Option<PhyCapabilities> = None;
+    let mut eapol: Option<EapolKey> = None;
 
     // management frames (type=0)
     if frame_type == 0 {
@@ -238,14 +764,48 @@ fn parse_wifi_packet(data: &[u8], timestamp: Duration) -> Option<Packet> {
             // beacon (0x08) or Probe Response (0x05)
             8 | 5 => {
                 ap_mac = Some(extract_mac(&wlan_data[10..16]));
+                bssid = Some(extract_mac(&wlan_data[16..22]));
 
                 // Extract capabilities field (at offset 34 for beacons)
                 if wlan_data.len() >= 36 {
                     let capabilities = u16::from_le_bytes([wlan_data[34], wlan_data[35]]);
-                    security = Some(parse_wifi_security(&wlan_data[24..], capabilities));
-                    ssid = parse_management_frame_body(&wlan_data[24..]);
+                    let (wifi_security, rsn_info) =
+                        parse_wifi_security(&wlan_data[24..], capabilities);
+                    security = Some(wifi_security);
+                    rsn = rsn_info;
+                    wps = parse_wps_element(&wlan_data[24..], 12);
+                    regulatory = parse_regulatory_elements(&wlan_data[24..], 12);
+                    phy = parse_phy_capabilities(&wlan_data[24..], 12, band);
+                    operating_channel = parse_operation_elements(&wlan_data[24..], 12);
+                    advertised_channel = find_ds_channel(&wlan_data[24..], 12)
+                        .or(operating_channel.and_then(|c| c.primary));
+                    ssid = parse_management_frame_body(&wlan_data[24..], 12);
+                    // probe responses always carry the real SSID
+                    hidden_ssid = frame_subtype == 8
+                        && find_ssid_element(&wlan_data[24..], 12).is_none_or(is_hidden_ssid);
                 }
             }
+            // Association Request (0x00) or Reassociation Request (0x02), sent by a client that
+            // has to name the network, hidden or not
+            0 | 2 => {
+                // the client's signal says nothing about the AP position
+                ap_mac = None;
+                bssid = Some(extract_mac(&wlan_data[16..22]));
+
+                // capability + listen interval, plus the current AP address for reassociations
+                let fixed_len = if frame_subtype == 0 { 4 } else { 10 };
+                ssid = parse_management_frame_body(&wlan_data[24..], fixed_len);
+            }
+            // Probe Request (0x04), a client looking for networks it knows (directed probe) or for
+            // any network (wildcard, empty SSID)
+            4 => {
+                ap_mac = None;
+                station = Some(extract_mac(&wlan_data[10..16]));
+                station_transmitted = true;
+                probe_request = true;
+                // no fixed parameters
+                ssid = parse_management_frame_body(&wlan_data[24..], 0);
+            }
             // Association/Reassociation Response
             1 | 3 => {
                 ap_mac = Some(extract_mac(&wlan_data[10..16]));
@@ -259,11 +819,36 @@ fn parse_wifi_packet(data: &[u8], timestamp: Duration) -> Option<Packet> {
     // data frames (type=2)
     else if frame_type == 2 {
         match (to_ds, from_ds) {
-            (1, 0) => ap_mac = Some(extract_mac(&wlan_data[4..10])),
-            (0, 1) => ap_mac = Some(extract_mac(&wlan_data[10..16])),
+            // station -> AP, the signal is the station's but still counts as an AP sighting
+            (1, 0) => {
+                ap_mac = Some(extract_mac(&wlan_data[4..10]));
+                bssid = ap_mac;
+                station = Some(extract_mac(&wlan_data[10..16]));
+                station_transmitted = true;
+            }
+            // AP -> station
+            (0, 1) => {
+                ap_mac = Some(extract_mac(&wlan_data[10..16]));
+                bssid = ap_mac;
+                station = Some(extract_mac(&wlan_data[4..10]));
+            }
             (0, 0) => ap_mac = Some(extract_mac(&wlan_data[16..22])),
             _ => return None,
         }
+
+        // QoS data frames have a QoS control field, followed by an HT control field when the
+        // order bit is set; protected frames can't be EAPOL
+        let protected = frame_control & 0x4000 != 0;
+        let mut header_len = 24;
+        if frame_subtype & 0x08 != 0 {
+            header_len += 2;
+            if frame_control & 0x8000 != 0 {
+                header_len += 4;
+            }
+        }
+        if bssid.is_some() && !protected {
+            eapol = wlan_data.get(header_len..).and_then(parse_eapol_key);
+        }
     } else {
         return None;
     }
@@ -272,113 +857,199 @@ fn parse_wifi_packet(data: &[u8], timestamp: Duration) -> Option<Packet> {
         timestamp,
         source_address: ap_mac,
         ssid,
-        signal_strength,
+        bssid,
+        hidden_ssid,
+        // group addressed frames have no single client
+        station: station.filter(|mac| mac[0] & 0x01 == 0),
+        station_transmitted,
+        probe_request,
+        signal_strength: signal.rssi(rssi_combine),
+        chain_signals: signal.chains,
+        noise: signal.noise,
         channel,
+        advertised_channel,
+        band,
+        operating_channel,
         security,
+        rsn,
+        wps,
+        regulatory,
+        phy,
+        eapol,
+        interface: interface.name.clone(),
     })
 }
 
-fn parse_wifi_security(frame_body: &[u8], capabilities: u16) -> WifiSecurity {
-    // check privacy bit (bit 4) in capability field
-    let privacy_enabled = (capabilities & 0x0010) != 0;
+/// The FCS is the CRC-32 of the frame, little endian.
+fn fcs_matches(frame: &[u8], fcs: &[u8]) -> bool {
+    fcs.try_into()
+        .is_ok_and(|fcs| crc32fast::hash(frame) == u32::from_le_bytes(fcs))
+}
 
-    if !privacy_enabled {
-        return WifiSecurity::Open;
-    }
+#[cfg(test)]
+mod tests_packets {
+    use super::*;
 
-    if frame_body.len() < 12 {
-        return WifiSecurity::Unknown;
+    #[test]
+    fn test_detect_capture_format() {
+        assert_eq!(
+            detect_capture_format(&[0x0a, 0x0d, 0x0d, 0x0a, 0x1c, 0x00]),
+            Some(CaptureFormat::PcapNg)
+        );
+        assert_eq!(
+            detect_capture_format(&[0xd4, 0xc3, 0xb2, 0xa1]),
+            Some(CaptureFormat::Pcap)
+        );
+        assert_eq!(
+            detect_capture_format(&[0xa1, 0xb2, 0x3c, 0x4d]),
+            Some(CaptureFormat::Pcap)
+        );
+        assert_eq!(detect_capture_format(b"$GPRMC"), None);
+        assert_eq!(detect_capture_format(&[0x0a]), None);
     }
 
-    let mut has_rsn = false;
-    let mut has_wpa = false;
-    let mut has_sae = false;
-    let mut has_psk = false;
-    let mut offset = 12; // skip fixed parameters
+    #[test]
+    fn test_interface_timestamp_resolution() {
+        let mut interface = CaptureInterface {
+            name: Arc::from("wlan1"),
+            linktype: DataLink::IEEE802_11_RADIOTAP,
+            snaplen: 0,
+            ts_resolution: 6,
+            ts_offset: 0,
+            fcs_len: 0,
+        };
+        let expected = Duration::new(1_759_320_000, 250_000_000);
 
-    while offset + 2 <= frame_body.len() {
-        let tag_number = frame_body[offset];
-        let tag_length = frame_body[offset + 1] as usize;
+        assert_eq!(interface.timestamp(1_759_320_000_250_000), expected);
 
-        if offset + 2 + tag_length > frame_body.len() {
-            break;
-        }
+        interface.ts_resolution = 9;
+        assert_eq!(interface.timestamp(1_759_320_000_250_000_000), expected);
 
-        match tag_number {
-            // RSN Information Element (WPA2/WPA3)
-            48 => {
-                has_rsn = true;
-
-                // parse AKM suites to detect WPA2/WPA3
-                if tag_length >= 8 {
-                    let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
-
-                    // skip version (2 bytes) + Group Cipher (4 bytes)
-                    if tag_data.len() >= 8 {
-                        // pairwise cipher suite count (2 bytes)
-                        let pairwise_count =
-                            u16::from_le_bytes([tag_data[6], tag_data[7]]) as usize;
-                        let pairwise_size = pairwise_count * 4;
-
-                        // AKM suite offset
-                        let akm_offset = 8 + pairwise_size;
-                        if tag_data.len() >= akm_offset + 2 {
-                            let akm_count = u16::from_le_bytes([
-                                tag_data[akm_offset],
-                                tag_data[akm_offset + 1],
-                            ]) as usize;
-
-                            // check AKM suites
-                            for i in 0..akm_count {
-                                let suite_offset = akm_offset + 2 + (i * 4);
-                                if tag_data.len() >= suite_offset + 4 {
-                                    let akm_type = tag_data[suite_offset + 3];
-                                    match akm_type {
-                                        2 => has_psk = true, // WPA2-PSK
-                                        8 => has_sae = true, // WPA3-SAE
-                                        _ => {}
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            // vendor specific - WPA
-            221 => {
-                if tag_length >= 8 {
-                    let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
-
-                    // check for WPA OUI (00:50:f2) and type 1
-                    if tag_data.len() >= 4
-                        && tag_data[0] == 0x00
-                        && tag_data[1] == 0x50
-                        && tag_data[2] == 0xf2
-                        && tag_data[3] == 0x01
-                    {
-                        has_wpa = true;
-                    }
-                }
-            }
-            _ => {}
-        }
+        // 2^-10 units
+        interface.ts_resolution = 0x80 | 10;
+        assert_eq!(interface.timestamp((1_759_320_000 << 10) + 256), expected);
 
-        offset += 2 + tag_length;
+        interface.ts_resolution = 6;
+        interface.ts_offset = 1_759_320_000;
+        assert_eq!(interface.timestamp(250_000), expected);
+
+        // negative offsets, as written by the u64 option
+        interface.ts_offset = 0xffff_ffff_ffff_fc18_u64 as i64;
+        assert_eq!(interface.timestamp(1_759_321_000_250_000), expected);
+        assert_eq!(interface.timestamp(250_000), Duration::ZERO);
     }
 
-    if has_rsn {
-        if has_sae && has_psk {
-            return WifiSecurity::WPA2WPA3; // transition mode
-        } else if has_sae {
-            return WifiSecurity::WPA3;
-        } else {
-            return WifiSecurity::WPA2;
-        }
-    } else if has_wpa {
-        return WifiSecurity::WPA;
-    } else if privacy_enabled {
-        return WifiSecurity::WEP;
+    #[test]
+    fn test_hidden_ssid() {
+        let interface = CaptureInterface {
+            name: Arc::from("wlan1"),
+            linktype: DataLink::IEEE802_11,
+            snaplen: 0,
+            ts_resolution: 6,
+            ts_offset: 0,
+            fcs_len: 0,
+        };
+        let bssid = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let client = [0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0xee];
+        let header = |subtype: u8, source: [u8; 6]| {
+            let mut header = vec![subtype << 4, 0x00, 0x00, 0x00];
+            header.extend_from_slice(&bssid);
+            header.extend_from_slice(&source);
+            header.extend_from_slice(&bssid);
+            header.extend_from_slice(&[0x00, 0x00]);
+            header
+        };
+
+        // beacon: timestamp, interval, capabilities, then a NUL-filled SSID
+        let mut beacon = header(8, bssid);
+        beacon.extend_from_slice(&[0; 8]);
+        beacon.extend_from_slice(&[0x64, 0x00, 0x01, 0x00]);
+        beacon.extend_from_slice(&[0x00, 0x04, 0x00, 0x00, 0x00, 0x00]);
+        let packet = parse_wifi_packet(
+            &beacon,
+            &interface,
+            Duration::ZERO,
+            false,
+            false,
+            RssiCombine::Combined,
+            &mut 0,
+        )
+        .unwrap();
+        assert!(packet.hidden_ssid);
+        assert_eq!(packet.ssid, None);
+        assert_eq!(packet.source_address, Some(bssid));
+
+        // association request: capabilities, listen interval, then the SSID
+        let mut assoc_request = header(0, client);
+        assoc_request.extend_from_slice(&[0x01, 0x00, 0x0a, 0x00]);
+        assoc_request.extend_from_slice(&[0x00, 0x06]);
+        assoc_request.extend_from_slice(b"Secret");
+        let packet = parse_wifi_packet(
+            &assoc_request,
+            &interface,
+            Duration::ZERO,
+            false,
+            false,
+            RssiCombine::Combined,
+            &mut 0,
+        )
+        .unwrap();
+        assert!(!packet.hidden_ssid);
+        assert_eq!(packet.ssid, Some(Ssid::new(b"Secret")));
+        assert_eq!(packet.bssid, Some(bssid));
+        assert_eq!(packet.source_address, None);
     }
 
-    WifiSecurity::Unknown
+    #[test]
+    fn test_fcs() {
+        let interface = CaptureInterface {
+            name: Arc::from("wlan1"),
+            linktype: DataLink::IEEE802_11,
+            snaplen: 0,
+            ts_resolution: 6,
+            ts_offset: 0,
+            fcs_len: 4,
+        };
+        let bssid = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let mut beacon = vec![0x80, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        beacon.extend_from_slice(&bssid);
+        beacon.extend_from_slice(&bssid);
+        beacon.extend_from_slice(&[0x00, 0x00]);
+        beacon.extend_from_slice(&[0; 8]);
+        beacon.extend_from_slice(&[0x64, 0x00, 0x01, 0x00]);
+        beacon.extend_from_slice(&[0x00, 0x04]);
+        beacon.extend_from_slice(b"Home");
+        let fcs = crc32fast::hash(&beacon).to_le_bytes();
+        assert!(fcs_matches(&beacon, &fcs));
+        assert!(!fcs_matches(&beacon[1..], &fcs));
+        beacon.extend_from_slice(&fcs);
+
+        // the FCS is not read as a truncated element
+        let mut bad_fcs_frames = 0;
+        let packet = parse_wifi_packet(
+            &beacon,
+            &interface,
+            Duration::ZERO,
+            false,
+            true,
+            RssiCombine::Combined,
+            &mut bad_fcs_frames,
+        )
+        .unwrap();
+        assert_eq!(packet.ssid, Some(Ssid::new(b"Home")));
+
+        assert!(
+            parse_wifi_packet(
+                &beacon,
+                &interface,
+                Duration::ZERO,
+                true,
+                true,
+                RssiCombine::Combined,
+                &mut bad_fcs_frames
+            )
+            .is_none()
+        );
+        assert_eq!(bad_fcs_frames, 1);
+    }
 }
@@ -0,0 +1,263 @@
+use crate::channel::{Band, ChannelWidth};
+use std::fmt;
+
+/// 802.11 amendment an AP implements, from its capability elements and supported rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum)]
+pub enum PhyGeneration {
+    B,
+    A,
+    G,
+    N,
+    Ac,
+    Ax,
+    Be,
+}
+
+impl PhyGeneration {
+    /// Wi-Fi Alliance generation name, for 802.11n and later.
+    pub fn wifi_name(&self) -> Option<&'static str> {
+        match self {
+            PhyGeneration::N => Some("Wi-Fi 4"),
+            PhyGeneration::Ac => Some("Wi-Fi 5"),
+            PhyGeneration::Ax => Some("Wi-Fi 6"),
+            PhyGeneration::Be => Some("Wi-Fi 7"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PhyGeneration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhyGeneration::B => write!(f, "802.11b"),
+            PhyGeneration::A => write!(f, "802.11a"),
+            PhyGeneration::G => write!(f, "802.11g"),
+            PhyGeneration::N => write!(f, "802.11n"),
+            PhyGeneration::Ac => write!(f, "802.11ac"),
+            PhyGeneration::Ax => write!(f, "802.11ax"),
+            PhyGeneration::Be => write!(f, "802.11be"),
+        }
+    }
+}
+
+/// PHY capabilities advertised in a beacon or probe response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhyCapabilities {
+    pub generation: PhyGeneration,
+    /// highest number of spatial streams the AP receives with
+    pub spatial_streams: Option<u8>,
+    /// channel widths the AP supports, sorted
+    pub widths: Vec<ChannelWidth>,
+}
+
+impl PhyCapabilities {
+    /// Keeps the best of both, as beacons and probe responses may not carry the same elements.
+    pub fn merge(&mut self, other: &PhyCapabilities) {
+        self.generation = self.generation.max(other.generation);
+        self.spatial_streams = self.spatial_streams.max(other.spatial_streams);
+        for width in &other.widths {
+            if !self.widths.contains(width) {
+                self.widths.push(*width);
+            }
+        }
+        self.widths.sort_unstable();
+    }
+
+    /// e.g. "802.11ax (Wi-Fi 6)".
+    pub fn generation_name(&self) -> String {
+        match self.generation.wifi_name() {
+            Some(name) => format!("{} ({})", self.generation, name),
+            None => self.generation.to_string(),
+        }
+    }
+}
+
+/// Returns the PHY capabilities of a beacon or probe response body, whose fixed parameters are
+/// `fixed_len` bytes long, captured on `band`. Returns `None` when the body has neither
+/// capability elements nor supported rates.
+pub fn parse_phy_capabilities(
+    frame_body: &[u8],
+    fixed_len: usize,
+    band: Option<Band>,
+) -> Option<PhyCapabilities> {
+    let mut has_rates = false;
+    let mut ofdm_rates = false;
+    let mut ht = false;
+    let mut vht = false;
+    let mut he = false;
+    let mut eht = false;
+    let mut spatial_streams: Option<u8> = None;
+    let mut widths = vec![ChannelWidth::Mhz20];
+    let mut offset = fixed_len; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+        let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
+
+        match tag_number {
+            // Supported Rates and Extended Supported Rates, in 500 kbps units
+            1 | 50 => {
+                has_rates |= !tag_data.is_empty();
+                // anything but 1, 2, 5.5 and 11 Mbps is OFDM (or a BSS membership selector)
+                ofdm_rates |= tag_data
+                    .iter()
+                    .map(|rate| rate & 0x7f)
+                    .any(|rate| !matches!(rate, 2 | 4 | 11 | 22) && rate <= 108);
+            }
+            // HT Capabilities: capability info (2 bytes), A-MPDU parameters (1), then the
+            // supported MCS set whose first 4 bytes are the RX MCS bitmask of 1 to 4 streams
+            45 => {
+                ht = true;
+                if let Some(&info) = tag_data.first()
+                    && info & 0x02 != 0
+                {
+                    widths.push(ChannelWidth::Mhz40);
+                }
+                if let Some(mcs) = tag_data.get(3..7) {
+                    let streams = mcs.iter().take_while(|&&bitmask| bitmask != 0).count() as u8;
+                    spatial_streams = spatial_streams.max(Some(streams).filter(|&n| n > 0));
+                }
+            }
+            61 => ht = true,
+            // VHT Capabilities: capability info (4 bytes), then the RX MCS map
+            191 => {
+                vht = true;
+                widths.push(ChannelWidth::Mhz80);
+                if let Some(&info) = tag_data.first() {
+                    match (info >> 2) & 0x03 {
+                        1 => widths.push(ChannelWidth::Mhz160),
+                        2 => widths.extend([ChannelWidth::Mhz160, ChannelWidth::Mhz80Plus80]),
+                        _ => {}
+                    }
+                }
+                if let Some(map) = tag_data.get(4..6) {
+                    spatial_streams = spatial_streams.max(mcs_map_streams(map));
+                }
+            }
+            192 => vht = true,
+            255 => match tag_data.first() {
+                // HE Capabilities: MAC capabilities (6 bytes), PHY capabilities (11), then the
+                // RX HE-MCS map for up to 80 MHz
+                Some(35) => {
+                    he = true;
+                    if let Some(&width_set) = tag_data.get(7) {
+                        if width_set & 0x02 != 0 {
+                            widths.push(ChannelWidth::Mhz40);
+                        }
+                        if width_set & 0x04 != 0 {
+                            widths.extend([ChannelWidth::Mhz40, ChannelWidth::Mhz80]);
+                        }
+                        if width_set & 0x08 != 0 {
+                            widths.push(ChannelWidth::Mhz160);
+                        }
+                        if width_set & 0x10 != 0 {
+                            widths.push(ChannelWidth::Mhz80Plus80);
+                        }
+                    }
+                    if let Some(map) = tag_data.get(18..20) {
+                        spatial_streams = spatial_streams.max(mcs_map_streams(map));
+                    }
+                }
+                Some(36) => he = true,
+                // EHT Capabilities: MAC capabilities (2 bytes), then PHY capabilities whose
+                // bit 1 is 320 MHz support in 6 GHz
+                Some(108) => {
+                    eht = true;
+                    if tag_data.get(3).is_some_and(|phy| phy & 0x02 != 0) {
+                        widths.push(ChannelWidth::Mhz320);
+                    }
+                }
+                Some(106) => eht = true,
+                _ => {}
+            },
+            _ => {}
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    let generation = if eht {
+        PhyGeneration::Be
+    } else if he {
+        PhyGeneration::Ax
+    } else if vht {
+        PhyGeneration::Ac
+    } else if ht {
+        PhyGeneration::N
+    } else if !has_rates {
+        return None;
+    } else if matches!(band, Some(Band::Ghz5) | Some(Band::Ghz6)) {
+        PhyGeneration::A
+    } else if ofdm_rates {
+        PhyGeneration::G
+    } else {
+        PhyGeneration::B
+    };
+
+    widths.sort_unstable();
+    widths.dedup();
+    Some(PhyCapabilities {
+        generation,
+        spatial_streams,
+        widths,
+    })
+}
+
+/// Number of spatial streams of a VHT or HE MCS map: 2 bits per stream count, 3 meaning
+/// unsupported.
+fn mcs_map_streams(map: &[u8]) -> Option<u8> {
+    let map = u16::from_le_bytes([map[0], map[1]]);
+    (1..=8u8)
+        .rev()
+        .find(|nss| (map >> ((nss - 1) * 2)) & 0x03 != 0x03)
+}
+
+#[cfg(test)]
+mod tests_phy {
+    use super::*;
+
+    const RATES_B: [u8; 6] = [1, 4, 0x82, 0x84, 0x0b, 0x16];
+    const RATES_G: [u8; 10] = [1, 8, 0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24];
+
+    #[test]
+    fn test_parse_phy_capabilities() {
+        let phy = parse_phy_capabilities(&RATES_B, 0, Some(Band::Ghz2_4)).unwrap();
+        assert_eq!(phy.generation, PhyGeneration::B);
+        assert_eq!(phy.widths, vec![ChannelWidth::Mhz20]);
+
+        let phy = parse_phy_capabilities(&RATES_G, 0, Some(Band::Ghz2_4)).unwrap();
+        assert_eq!(phy.generation, PhyGeneration::G);
+
+        // HT 20/40 MHz with 2 streams, VHT 160 MHz with 4 streams
+        let mut body = RATES_G.to_vec();
+        body.extend_from_slice(&[45, 26, 0x02, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00]);
+        body.extend_from_slice(&[0; 19]);
+        body.extend_from_slice(&[191, 12, 0x04, 0, 0, 0, 0xaa, 0xff, 0, 0, 0, 0, 0, 0]);
+        let phy = parse_phy_capabilities(&body, 0, Some(Band::Ghz5)).unwrap();
+        assert_eq!(phy.generation, PhyGeneration::Ac);
+        assert_eq!(phy.generation_name(), "802.11ac (Wi-Fi 5)");
+        assert_eq!(phy.spatial_streams, Some(4));
+        assert_eq!(
+            phy.widths,
+            vec![
+                ChannelWidth::Mhz20,
+                ChannelWidth::Mhz40,
+                ChannelWidth::Mhz80,
+                ChannelWidth::Mhz160
+            ]
+        );
+
+        // EHT capabilities with 320 MHz
+        let eht = [255, 12, 108, 0, 0, 0x02, 0, 0, 0, 0, 0, 0, 0, 0];
+        let phy = parse_phy_capabilities(&eht, 0, Some(Band::Ghz6)).unwrap();
+        assert_eq!(phy.generation, PhyGeneration::Be);
+        assert!(phy.widths.contains(&ChannelWidth::Mhz320));
+
+        assert_eq!(parse_phy_capabilities(&[0, 0], 0, None), None);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::channel::Band;
+use std::fmt;
+
+/// Channels allowed by the Country element, with their maximum transmit power.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubBand {
+    pub first_channel: u8,
+    pub channel_count: u8,
+    /// maximum transmit power in dBm
+    pub max_power: i8,
+}
+
+impl SubBand {
+    /// Channels are consecutive on 2.4 GHz, and 20 MHz (4 channel numbers) apart above.
+    fn contains(&self, channel: u8) -> bool {
+        let step = if self.first_channel <= 14 { 1 } else { 4 };
+        channel >= self.first_channel
+            && (channel - self.first_channel).is_multiple_of(step)
+            && ((channel - self.first_channel) / step) < self.channel_count
+    }
+
+    fn is_2_4ghz(&self) -> bool {
+        self.first_channel <= 14
+    }
+}
+
+/// Regulatory information advertised by an AP in its Country, Power Constraint and TPC Report
+/// elements.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegulatoryInfo {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    /// third byte of the country string: ' ' any environment, 'I' indoor, 'O' outdoor
+    pub environment: Option<u8>,
+    pub sub_bands: Vec<SubBand>,
+    /// dB to subtract from the sub-band maximum power
+    pub power_constraint: Option<u8>,
+    /// transmit power of the frame, from the TPC report
+    pub tx_power: Option<i8>,
+}
+
+impl RegulatoryInfo {
+    pub fn is_empty(&self) -> bool {
+        *self == RegulatoryInfo::default()
+    }
+
+    /// Fills the fields missing here with those of `other`.
+    pub fn merge(&mut self, other: &RegulatoryInfo) {
+        if self.country.is_none() {
+            self.country = other.country.clone();
+            self.environment = other.environment;
+        }
+        if self.sub_bands.is_empty() {
+            self.sub_bands = other.sub_bands.clone();
+        }
+        self.power_constraint = self.power_constraint.or(other.power_constraint);
+        self.tx_power = self.tx_power.or(other.tx_power);
+    }
+
+    /// Country code with its environment, e.g. "DE (indoor)".
+    pub fn country_name(&self) -> Option<String> {
+        let country = self.country.as_ref()?;
+        Some(match self.environment {
+            Some(b'I') => format!("{} (indoor)", country),
+            Some(b'O') => format!("{} (outdoor)", country),
+            _ => country.clone(),
+        })
+    }
+
+    /// Maximum transmit power allowed on `channel`, in dBm: the sub-band maximum less the power
+    /// constraint.
+    pub fn max_power(&self, channel: u8) -> Option<i8> {
+        let constraint = self.power_constraint.unwrap_or(0).min(i8::MAX as u8) as i8;
+        self.sub_bands
+            .iter()
+            .find(|sub_band| sub_band.contains(channel))
+            .map(|sub_band| sub_band.max_power.saturating_sub(constraint))
+    }
+
+    /// Ways the AP breaks the rules of its own Country element on `channel`. Only the sub-bands
+    /// of the channel's band are checked, as many APs only list the band they operate in, and
+    /// 6 GHz is left out since its channels are described by operating classes instead.
+    pub fn violations(&self, channel: Option<u8>, band: Option<Band>) -> Vec<RegulatoryViolation> {
+        let mut violations = Vec::new();
+        let Some(channel) = channel else {
+            return violations;
+        };
+
+        let channel_2_4ghz = match band {
+            Some(Band::Ghz6) => return violations,
+            Some(band) => band == Band::Ghz2_4,
+            None => channel <= 14,
+        };
+        let same_band = self
+            .sub_bands
+            .iter()
+            .any(|sub_band| sub_band.is_2_4ghz() == channel_2_4ghz);
+        if !same_band {
+            return violations;
+        }
+
+        match self.max_power(channel) {
+            None => violations.push(RegulatoryViolation::Channel(channel)),
+            Some(max_power) => {
+                if let Some(tx_power) = self.tx_power
+                    && tx_power > max_power
+                {
+                    violations.push(RegulatoryViolation::Power {
+                        tx_power,
+                        max_power,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegulatoryViolation {
+    /// the channel is not in any advertised sub-band
+    Channel(u8),
+    /// the TPC report exceeds the sub-band maximum power less the power constraint
+    Power { tx_power: i8, max_power: i8 },
+}
+
+impl fmt::Display for RegulatoryViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegulatoryViolation::Channel(channel) => {
+                write!(f, "channel {} not allowed", channel)
+            }
+            RegulatoryViolation::Power {
+                tx_power,
+                max_power,
+            } => write!(
+                f,
+                "TX power {} dBm above the {} dBm limit",
+                tx_power, max_power
+            ),
+        }
+    }
+}
+
+/// Returns the regulatory elements of a beacon or probe response body, whose fixed parameters
+/// are `fixed_len` bytes long, or `None` if it has none.
+pub fn parse_regulatory_elements(frame_body: &[u8], fixed_len: usize) -> Option<RegulatoryInfo> {
+    let mut info = RegulatoryInfo::default();
+    let mut offset = fixed_len; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+        let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
+
+        match tag_number {
+            // Country: country string (3 bytes), then triplets, padded to an even length
+            7 if tag_data.len() >= 3 => {
+                if tag_data[..2].iter().all(u8::is_ascii_alphabetic) {
+                    info.country = Some(String::from_utf8_lossy(&tag_data[..2]).to_uppercase());
+                    info.environment = Some(tag_data[2]);
+                }
+                info.sub_bands = tag_data[3..]
+                    .chunks_exact(3)
+                    // first bytes above 200 start an operating extension triplet
+                    .filter(|triplet| triplet[0] != 0 && triplet[0] <= 200)
+                    .map(|triplet| SubBand {
+                        first_channel: triplet[0],
+                        channel_count: triplet[1],
+                        max_power: triplet[2] as i8,
+                    })
+                    .collect();
+            }
+            // Power Constraint
+            32 if !tag_data.is_empty() => info.power_constraint = Some(tag_data[0]),
+            // TPC Report: transmit power, link margin
+            35 if !tag_data.is_empty() => info.tx_power = Some(tag_data[0] as i8),
+            _ => {}
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    (!info.is_empty()).then_some(info)
+}
+
+#[cfg(test)]
+mod tests_regulatory {
+    use super::*;
+
+    #[test]
+    fn test_parse_regulatory_elements() {
+        // DE indoor, channels 1-13 at 20 dBm, 36-48 at 23 dBm, then an operating extension
+        let mut body = vec![
+            7, 15, b'D', b'E', b'I', 1, 13, 20, 36, 4, 23, 201, 1, 0, 0, 0, 0,
+        ];
+        body.extend_from_slice(&[32, 1, 3, 35, 2, 23, 0]);
+
+        let info = parse_regulatory_elements(&body, 0).unwrap();
+        assert_eq!(info.country_name().as_deref(), Some("DE (indoor)"));
+        assert_eq!(info.sub_bands.len(), 2);
+        assert_eq!(info.power_constraint, Some(3));
+        // 23 dBm less the 3 dB power constraint
+        assert_eq!(info.max_power(44), Some(20));
+        assert_eq!(info.max_power(46), None);
+
+        assert_eq!(
+            info.violations(Some(40), Some(Band::Ghz5)),
+            vec![RegulatoryViolation::Power {
+                tx_power: 23,
+                max_power: 20
+            }]
+        );
+        assert!(info.violations(Some(5), Some(Band::Ghz6)).is_empty());
+        assert_eq!(
+            info.violations(Some(6), None),
+            vec![RegulatoryViolation::Power {
+                tx_power: 23,
+                max_power: 17
+            }]
+        );
+        assert_eq!(
+            info.violations(Some(14), Some(Band::Ghz2_4)),
+            vec![RegulatoryViolation::Channel(14)]
+        );
+        assert_eq!(
+            info.violations(Some(149), Some(Band::Ghz5)),
+            vec![RegulatoryViolation::Channel(149)]
+        );
+
+        assert_eq!(parse_regulatory_elements(&[0, 2, b'h', b'i'], 0), None);
+    }
+}
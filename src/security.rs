@@ -0,0 +1,583 @@
+use std::fmt;
+
+const IEEE_OUI: [u8; 3] = [0x00, 0x0f, 0xac];
+const MICROSOFT_OUI: [u8; 3] = [0x00, 0x50, 0xf2];
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum WifiSecurity {
+    Open,
+    WEP,
+    WPA,
+    WPA2,
+    /// WPA/WPA2 mixed mode, with both a WPA and an RSN element
+    WPAWPA2,
+    WPA3,
+    WPA2WPA3,
+    Unknown,
+}
+
+impl fmt::Display for WifiSecurity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WifiSecurity::Open => write!(f, "Open"),
+            WifiSecurity::WEP => write!(f, "WEP"),
+            WifiSecurity::WPA => write!(f, "WPA"),
+            WifiSecurity::WPA2 => write!(f, "WPA2"),
+            WifiSecurity::WPAWPA2 => write!(f, "WPA/WPA2"),
+            WifiSecurity::WPA3 => write!(f, "WPA3"),
+            WifiSecurity::WPA2WPA3 => write!(f, "WPA2/WPA3"),
+            WifiSecurity::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// Authentication and key management suite, from the RSN element (00-0F-AC:n) or the WPA
+/// vendor element (00-50-F2:n).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Akm {
+    Ieee8021x,
+    Psk,
+    FtIeee8021x,
+    FtPsk,
+    Ieee8021xSha256,
+    PskSha256,
+    Tdls,
+    Sae,
+    FtSae,
+    ApPeerKey,
+    /// 802.1X Suite B, SHA-256
+    SuiteB,
+    /// 802.1X Suite B 192-bit, SHA-384 (WPA3-Enterprise 192-bit)
+    SuiteB192,
+    FtIeee8021xSha384,
+    FilsSha256,
+    FilsSha384,
+    FtFilsSha256,
+    FtFilsSha384,
+    Owe,
+    FtPskSha384,
+    PskSha384,
+    SaeExtKey,
+    FtSaeExtKey,
+    /// any other suite, as OUI and type
+    Other([u8; 4]),
+}
+
+impl Akm {
+    fn from_rsn_suite(suite: [u8; 4]) -> Self {
+        if suite[..3] != IEEE_OUI {
+            return Akm::Other(suite);
+        }
+        match suite[3] {
+            1 => Akm::Ieee8021x,
+            2 => Akm::Psk,
+            3 => Akm::FtIeee8021x,
+            4 => Akm::FtPsk,
+            5 => Akm::Ieee8021xSha256,
+            6 => Akm::PskSha256,
+            7 => Akm::Tdls,
+            8 => Akm::Sae,
+            9 => Akm::FtSae,
+            10 => Akm::ApPeerKey,
+            11 => Akm::SuiteB,
+            12 => Akm::SuiteB192,
+            13 => Akm::FtIeee8021xSha384,
+            14 => Akm::FilsSha256,
+            15 => Akm::FilsSha384,
+            16 => Akm::FtFilsSha256,
+            17 => Akm::FtFilsSha384,
+            18 => Akm::Owe,
+            19 => Akm::FtPskSha384,
+            20 => Akm::PskSha384,
+            24 => Akm::SaeExtKey,
+            25 => Akm::FtSaeExtKey,
+            _ => Akm::Other(suite),
+        }
+    }
+
+    fn from_wpa_suite(suite: [u8; 4]) -> Self {
+        match (suite[..3] == MICROSOFT_OUI, suite[3]) {
+            (true, 1) => Akm::Ieee8021x,
+            (true, 2) => Akm::Psk,
+            _ => Akm::Other(suite),
+        }
+    }
+
+    /// The authentication method behind this AKM, `None` for peer-to-peer suites.
+    pub fn auth_method(&self) -> Option<AuthMethod> {
+        match self {
+            Akm::Psk | Akm::FtPsk | Akm::PskSha256 | Akm::FtPskSha384 | Akm::PskSha384 => {
+                Some(AuthMethod::Psk)
+            }
+            Akm::Sae | Akm::FtSae | Akm::SaeExtKey | Akm::FtSaeExtKey => Some(AuthMethod::Sae),
+            Akm::Ieee8021x
+            | Akm::FtIeee8021x
+            | Akm::Ieee8021xSha256
+            | Akm::SuiteB
+            | Akm::SuiteB192
+            | Akm::FtIeee8021xSha384
+            | Akm::FilsSha256
+            | Akm::FilsSha384
+            | Akm::FtFilsSha256
+            | Akm::FtFilsSha384 => Some(AuthMethod::Ieee8021x),
+            Akm::Owe => Some(AuthMethod::Owe),
+            Akm::Tdls | Akm::ApPeerKey | Akm::Other(_) => None,
+        }
+    }
+
+    /// AKMs only allowed in WPA3 (SAE, OWE and the 192-bit enterprise mode).
+    fn is_wpa3(&self) -> bool {
+        matches!(
+            self,
+            Akm::Sae | Akm::FtSae | Akm::SaeExtKey | Akm::FtSaeExtKey | Akm::Owe | Akm::SuiteB192
+        )
+    }
+}
+
+impl fmt::Display for Akm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Akm::Ieee8021x => write!(f, "802.1X"),
+            Akm::Psk => write!(f, "PSK"),
+            Akm::FtIeee8021x => write!(f, "FT-802.1X"),
+            Akm::FtPsk => write!(f, "FT-PSK"),
+            Akm::Ieee8021xSha256 => write!(f, "802.1X-SHA256"),
+            Akm::PskSha256 => write!(f, "PSK-SHA256"),
+            Akm::Tdls => write!(f, "TDLS"),
+            Akm::Sae => write!(f, "SAE"),
+            Akm::FtSae => write!(f, "FT-SAE"),
+            Akm::ApPeerKey => write!(f, "APPeerKey"),
+            Akm::SuiteB => write!(f, "802.1X-SuiteB"),
+            Akm::SuiteB192 => write!(f, "802.1X-SuiteB-192"),
+            Akm::FtIeee8021xSha384 => write!(f, "FT-802.1X-SHA384"),
+            Akm::FilsSha256 => write!(f, "FILS-SHA256"),
+            Akm::FilsSha384 => write!(f, "FILS-SHA384"),
+            Akm::FtFilsSha256 => write!(f, "FT-FILS-SHA256"),
+            Akm::FtFilsSha384 => write!(f, "FT-FILS-SHA384"),
+            Akm::Owe => write!(f, "OWE"),
+            Akm::FtPskSha384 => write!(f, "FT-PSK-SHA384"),
+            Akm::PskSha384 => write!(f, "PSK-SHA384"),
+            Akm::SaeExtKey => write!(f, "SAE-EXT-KEY"),
+            Akm::FtSaeExtKey => write!(f, "FT-SAE-EXT-KEY"),
+            Akm::Other(suite) => write!(
+                f,
+                "{:02x}-{:02x}-{:02x}:{}",
+                suite[0], suite[1], suite[2], suite[3]
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuthMethod {
+    Open,
+    Psk,
+    Sae,
+    /// Enterprise, with a RADIUS server behind the AP
+    Ieee8021x,
+    /// Opportunistic Wireless Encryption (Enhanced Open)
+    Owe,
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthMethod::Open => write!(f, "Open"),
+            AuthMethod::Psk => write!(f, "PSK"),
+            AuthMethod::Sae => write!(f, "SAE"),
+            AuthMethod::Ieee8021x => write!(f, "802.1X"),
+            AuthMethod::Owe => write!(f, "OWE"),
+        }
+    }
+}
+
+/// The authentication methods offered by a network, sorted. WEP counts as a pre-shared key.
+pub fn auth_methods(security: Option<&WifiSecurity>, akms: &[Akm]) -> Vec<AuthMethod> {
+    let mut methods: Vec<AuthMethod> = match security {
+        Some(WifiSecurity::Open) => vec![AuthMethod::Open],
+        Some(WifiSecurity::WEP) => vec![AuthMethod::Psk],
+        _ => akms.iter().filter_map(Akm::auth_method).collect(),
+    };
+    methods.sort_unstable();
+    methods.dedup();
+    methods
+}
+
+/// Cipher suite, from the RSN element (00-0F-AC:n) or the WPA vendor element (00-50-F2:n).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cipher {
+    /// pairwise only: stations use the group cipher
+    UseGroup,
+    Wep40,
+    Tkip,
+    Ccmp128,
+    Wep104,
+    BipCmac128,
+    GroupNotAllowed,
+    Gcmp128,
+    Gcmp256,
+    Ccmp256,
+    BipGmac128,
+    BipGmac256,
+    BipCmac256,
+    /// any other suite, as OUI and type
+    Other([u8; 4]),
+}
+
+impl Cipher {
+    fn from_rsn_suite(suite: [u8; 4]) -> Self {
+        if suite[..3] != IEEE_OUI {
+            return Cipher::Other(suite);
+        }
+        match suite[3] {
+            0 => Cipher::UseGroup,
+            1 => Cipher::Wep40,
+            2 => Cipher::Tkip,
+            4 => Cipher::Ccmp128,
+            5 => Cipher::Wep104,
+            6 => Cipher::BipCmac128,
+            7 => Cipher::GroupNotAllowed,
+            8 => Cipher::Gcmp128,
+            9 => Cipher::Gcmp256,
+            10 => Cipher::Ccmp256,
+            11 => Cipher::BipGmac128,
+            12 => Cipher::BipGmac256,
+            13 => Cipher::BipCmac256,
+            _ => Cipher::Other(suite),
+        }
+    }
+
+    fn from_wpa_suite(suite: [u8; 4]) -> Self {
+        match (suite[..3] == MICROSOFT_OUI, suite[3]) {
+            (true, 0) => Cipher::UseGroup,
+            (true, 1) => Cipher::Wep40,
+            (true, 2) => Cipher::Tkip,
+            (true, 4) => Cipher::Ccmp128,
+            (true, 5) => Cipher::Wep104,
+            _ => Cipher::Other(suite),
+        }
+    }
+}
+
+impl fmt::Display for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cipher::UseGroup => write!(f, "Group"),
+            Cipher::Wep40 => write!(f, "WEP-40"),
+            Cipher::Tkip => write!(f, "TKIP"),
+            Cipher::Ccmp128 => write!(f, "CCMP-128"),
+            Cipher::Wep104 => write!(f, "WEP-104"),
+            Cipher::BipCmac128 => write!(f, "BIP-CMAC-128"),
+            Cipher::GroupNotAllowed => write!(f, "None"),
+            Cipher::Gcmp128 => write!(f, "GCMP-128"),
+            Cipher::Gcmp256 => write!(f, "GCMP-256"),
+            Cipher::Ccmp256 => write!(f, "CCMP-256"),
+            Cipher::BipGmac128 => write!(f, "BIP-GMAC-128"),
+            Cipher::BipGmac256 => write!(f, "BIP-GMAC-256"),
+            Cipher::BipCmac256 => write!(f, "BIP-CMAC-256"),
+            Cipher::Other(suite) => write!(
+                f,
+                "{:02x}-{:02x}-{:02x}:{}",
+                suite[0], suite[1], suite[2], suite[3]
+            ),
+        }
+    }
+}
+
+/// Protected Management Frames (802.11w), from the MFPC and MFPR bits of the RSN capabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pmf {
+    Disabled,
+    Capable,
+    Required,
+}
+
+impl fmt::Display for Pmf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pmf::Disabled => write!(f, "disabled"),
+            Pmf::Capable => write!(f, "capable"),
+            Pmf::Required => write!(f, "required"),
+        }
+    }
+}
+
+/// Suites advertised in the RSN element, plus those of the WPA element for mixed mode APs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RsnInfo {
+    pub group_cipher: Option<Cipher>,
+    pub pairwise_ciphers: Vec<Cipher>,
+    pub akms: Vec<Akm>,
+    /// `None` when the element has no capabilities field, as with WPA
+    pub pmf: Option<Pmf>,
+}
+
+impl RsnInfo {
+    /// Stations can only use TKIP for unicast traffic.
+    pub fn tkip_only(&self) -> bool {
+        if self.pairwise_ciphers.is_empty() {
+            return self.group_cipher == Some(Cipher::Tkip);
+        }
+        self.pairwise_ciphers.iter().all(|cipher| match cipher {
+            Cipher::UseGroup => self.group_cipher == Some(Cipher::Tkip),
+            cipher => *cipher == Cipher::Tkip,
+        })
+    }
+
+    /// Adds the suites of `other` missing here, keeping this group cipher and PMF.
+    fn merge(&mut self, other: &RsnInfo) {
+        self.group_cipher = self.group_cipher.or(other.group_cipher);
+        for cipher in &other.pairwise_ciphers {
+            if !self.pairwise_ciphers.contains(cipher) {
+                self.pairwise_ciphers.push(*cipher);
+            }
+        }
+        for akm in &other.akms {
+            if !self.akms.contains(akm) {
+                self.akms.push(*akm);
+            }
+        }
+    }
+
+    /// Parses the body of an RSN element, or of a WPA element after its OUI and type: version,
+    /// group cipher, pairwise cipher list, AKM list, then RSN capabilities. Every field after the
+    /// version is optional, and truncated elements yield the fields read so far.
+    fn parse(data: &[u8], suite_oui: [u8; 3]) -> Self {
+        let is_rsn = suite_oui == IEEE_OUI;
+        let cipher = |suite| {
+            if is_rsn {
+                Cipher::from_rsn_suite(suite)
+            } else {
+                Cipher::from_wpa_suite(suite)
+            }
+        };
+        let akm = |suite| {
+            if is_rsn {
+                Akm::from_rsn_suite(suite)
+            } else {
+                Akm::from_wpa_suite(suite)
+            }
+        };
+
+        let mut info = RsnInfo {
+            group_cipher: read_suite(data, 2).map(cipher),
+            ..Default::default()
+        };
+
+        let mut offset = 6; // skip version (2 bytes) + group cipher (4 bytes)
+        let Some(pairwise) = read_suite_list(data, &mut offset) else {
+            return info;
+        };
+        info.pairwise_ciphers = pairwise.into_iter().map(cipher).collect();
+
+        let Some(akms) = read_suite_list(data, &mut offset) else {
+            return info;
+        };
+        info.akms = akms.into_iter().map(akm).collect();
+
+        if is_rsn && let Some(capabilities) = read_u16(data, offset) {
+            // bit 6 MFPR, bit 7 MFPC
+            info.pmf = Some(
+                match (capabilities & 0x0040 != 0, capabilities & 0x0080 != 0) {
+                    (true, _) => Pmf::Required,
+                    (false, true) => Pmf::Capable,
+                    (false, false) => Pmf::Disabled,
+                },
+            );
+        }
+        info
+    }
+}
+
+/// Returns the security of a beacon or probe response, with the suites of its RSN and WPA
+/// elements. APs advertising both run WPA/WPA2 mixed mode.
+pub fn parse_wifi_security(frame_body: &[u8], capabilities: u16) -> (WifiSecurity, RsnInfo) {
+    // check privacy bit (bit 4) in capability field
+    let privacy_enabled = (capabilities & 0x0010) != 0;
+
+    if !privacy_enabled {
+        return (WifiSecurity::Open, RsnInfo::default());
+    }
+
+    if frame_body.len() < 12 {
+        return (WifiSecurity::Unknown, RsnInfo::default());
+    }
+
+    let mut rsn: Option<RsnInfo> = None;
+    let mut wpa: Option<RsnInfo> = None;
+    let mut offset = 12; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+        let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
+
+        match tag_number {
+            // RSN Information Element (WPA2/WPA3)
+            48 => rsn = Some(RsnInfo::parse(tag_data, IEEE_OUI)),
+            // vendor specific - WPA, OUI 00:50:f2 and type 1
+            221 if tag_data.len() >= 4 && tag_data[..3] == MICROSOFT_OUI && tag_data[3] == 1 => {
+                wpa = Some(RsnInfo::parse(&tag_data[4..], MICROSOFT_OUI));
+            }
+            _ => {}
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    if let Some(mut rsn) = rsn {
+        let wpa3 = rsn.akms.iter().filter(|akm| akm.is_wpa3()).count();
+        let security = if wpa3 == 0 && wpa.is_some() {
+            WifiSecurity::WPAWPA2
+        } else if wpa3 == 0 {
+            WifiSecurity::WPA2
+        } else if wpa3
+            < rsn
+                .akms
+                .iter()
+                .filter(|akm| akm.auth_method().is_some())
+                .count()
+        {
+            WifiSecurity::WPA2WPA3 // transition mode
+        } else {
+            WifiSecurity::WPA3
+        };
+        if let Some(wpa) = wpa {
+            rsn.merge(&wpa);
+        }
+        return (security, rsn);
+    }
+    if let Some(wpa) = wpa {
+        return (WifiSecurity::WPA, wpa);
+    }
+
+    (WifiSecurity::WEP, RsnInfo::default())
+}
+
+/// Reads a suite count and the suites that follow it, advancing `offset` past the list.
+fn read_suite_list(data: &[u8], offset: &mut usize) -> Option<Vec<[u8; 4]>> {
+    let count = read_u16(data, *offset)? as usize;
+    let suites = (0..count)
+        .map(|i| read_suite(data, *offset + 2 + i * 4))
+        .collect::<Option<Vec<[u8; 4]>>>()?;
+    *offset += 2 + count * 4;
+    Some(suites)
+}
+
+fn read_suite(data: &[u8], offset: usize) -> Option<[u8; 4]> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+#[cfg(test)]
+mod tests_security {
+    use super::*;
+
+    /// Beacon fixed parameters followed by an RSN element with a CCMP pairwise cipher, the given
+    /// AKM suite types and no capabilities set.
+    fn rsn_beacon_body(akm_types: &[u8]) -> Vec<u8> {
+        let mut rsn = vec![
+            0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x01, 0x00, 0x00, 0x0f, 0xac, 0x04,
+        ];
+        rsn.extend_from_slice(&(akm_types.len() as u16).to_le_bytes());
+        for akm_type in akm_types {
+            rsn.extend_from_slice(&[0x00, 0x0f, 0xac, *akm_type]);
+        }
+        rsn.extend_from_slice(&[0x00, 0x00]);
+
+        let mut body = vec![0; 12];
+        body.extend_from_slice(&[48, rsn.len() as u8]);
+        body.extend_from_slice(&rsn);
+        body
+    }
+
+    #[test]
+    fn test_parse_wifi_security() {
+        let (security, RsnInfo { akms, .. }) =
+            parse_wifi_security(&rsn_beacon_body(&[1, 3]), 0x0011);
+        assert_eq!(security, WifiSecurity::WPA2);
+        assert_eq!(akms, vec![Akm::Ieee8021x, Akm::FtIeee8021x]);
+        assert_eq!(
+            auth_methods(Some(&security), &akms),
+            vec![AuthMethod::Ieee8021x]
+        );
+
+        let (security, RsnInfo { akms, .. }) =
+            parse_wifi_security(&rsn_beacon_body(&[2, 8]), 0x0011);
+        assert_eq!(security, WifiSecurity::WPA2WPA3);
+        assert_eq!(
+            auth_methods(Some(&security), &akms),
+            vec![AuthMethod::Psk, AuthMethod::Sae]
+        );
+
+        let (security, RsnInfo { akms, .. }) = parse_wifi_security(&rsn_beacon_body(&[18]), 0x0011);
+        assert_eq!(security, WifiSecurity::WPA3);
+        assert_eq!(auth_methods(Some(&security), &akms), vec![AuthMethod::Owe]);
+
+        let (security, RsnInfo { akms, .. }) = parse_wifi_security(&rsn_beacon_body(&[12]), 0x0011);
+        assert_eq!(security, WifiSecurity::WPA3);
+        assert_eq!(akms[0].to_string(), "802.1X-SuiteB-192");
+
+        let (security, RsnInfo { akms, .. }) = parse_wifi_security(&[0; 12], 0x0011);
+        assert_eq!(security, WifiSecurity::WEP);
+        assert!(akms.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ciphers_and_pmf() {
+        let (_, rsn) = parse_wifi_security(&rsn_beacon_body(&[2]), 0x0011);
+        assert_eq!(rsn.group_cipher, Some(Cipher::Ccmp128));
+        assert_eq!(rsn.pairwise_ciphers, vec![Cipher::Ccmp128]);
+        assert_eq!(rsn.pmf, Some(Pmf::Disabled));
+        assert!(!rsn.tkip_only());
+
+        // TKIP group and pairwise, MFPC and MFPR set
+        let mut rsn_element = vec![0x01, 0x00, 0x00, 0x0f, 0xac, 0x02, 0x01, 0x00];
+        rsn_element
+            .extend_from_slice(&[0x00, 0x0f, 0xac, 0x02, 0x01, 0x00, 0x00, 0x0f, 0xac, 0x08]);
+        rsn_element.extend_from_slice(&[0xc0, 0x00]);
+        let rsn = RsnInfo::parse(&rsn_element, IEEE_OUI);
+        assert!(rsn.tkip_only());
+        assert_eq!(rsn.akms, vec![Akm::Sae]);
+        assert_eq!(rsn.pmf, Some(Pmf::Required));
+
+        // WPA element body without capabilities
+        let wpa_element = [
+            0x01, 0x00, 0x00, 0x50, 0xf2, 0x02, 0x02, 0x00, 0x00, 0x50, 0xf2, 0x04, 0x00, 0x50,
+            0xf2, 0x02, 0x01, 0x00, 0x00, 0x50, 0xf2, 0x02,
+        ];
+        let wpa = RsnInfo::parse(&wpa_element, MICROSOFT_OUI);
+        assert_eq!(wpa.group_cipher, Some(Cipher::Tkip));
+        assert_eq!(wpa.pairwise_ciphers, vec![Cipher::Ccmp128, Cipher::Tkip]);
+        assert_eq!(wpa.akms, vec![Akm::Psk]);
+        assert_eq!(wpa.pmf, None);
+        assert!(!wpa.tkip_only());
+
+        // mixed mode: RSN with CCMP, then a WPA element with TKIP
+        let mut body = rsn_beacon_body(&[2]);
+        body.extend_from_slice(&[221, 26, 0x00, 0x50, 0xf2, 0x01]);
+        body.extend_from_slice(&wpa_element);
+        let (security, rsn) = parse_wifi_security(&body, 0x0011);
+        assert_eq!(security, WifiSecurity::WPAWPA2);
+        assert_eq!(security.to_string(), "WPA/WPA2");
+        assert_eq!(rsn.group_cipher, Some(Cipher::Ccmp128));
+        assert_eq!(rsn.pairwise_ciphers, vec![Cipher::Ccmp128, Cipher::Tkip]);
+        assert_eq!(rsn.akms, vec![Akm::Psk]);
+        assert_eq!(rsn.pmf, Some(Pmf::Disabled));
+
+        // truncated after the pairwise cipher list
+        let rsn = RsnInfo::parse(&rsn_element[..14], IEEE_OUI);
+        assert_eq!(rsn.pairwise_ciphers, vec![Cipher::Tkip]);
+        assert!(rsn.akms.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::geo::Observation;
+use radiotap::RadiotapIterator;
+use radiotap::field::{self, Antenna, AntennaNoise, AntennaSignal, Kind};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How the per-chain signals of a frame are combined into the RSSI used for distance estimation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RssiCombine {
+    /// signal of the first radiotap namespace, as combined by the driver
+    #[default]
+    Combined,
+    /// strongest chain
+    Max,
+    /// average power of the chains
+    Mean,
+    /// first antenna only
+    Primary,
+}
+
+impl fmt::Display for RssiCombine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RssiCombine::Combined => write!(f, "combined"),
+            RssiCombine::Max => write!(f, "max"),
+            RssiCombine::Mean => write!(f, "mean"),
+            RssiCombine::Primary => write!(f, "primary"),
+        }
+    }
+}
+
+/// Signal of one receive chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainSignal {
+    /// antenna index, when the adapter reports it
+    pub antenna: Option<u8>,
+    pub signal: i8,
+}
+
+/// Signal and noise reported in the radiotap header of a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RadiotapSignal {
+    /// signal of the first radiotap namespace, combined over the chains by the driver
+    pub combined: Option<i8>,
+    /// per-chain signals from the extended presence words, empty for single chain adapters
+    pub chains: Vec<ChainSignal>,
+    /// noise floor in dBm
+    pub noise: Option<i8>,
+}
+
+impl RadiotapSignal {
+    /// Reads the signal fields of every radiotap namespace. Adapters with several chains repeat
+    /// the antenna signal (and antenna index) in one extra namespace per chain, after the
+    /// combined signal of the first namespace.
+    pub fn from_radiotap(data: &[u8]) -> Self {
+        let mut signal = RadiotapSignal::default();
+        let Ok(iterator) = RadiotapIterator::from_bytes(data) else {
+            return signal;
+        };
+
+        // (antenna, signal) of each namespace carrying a signal
+        let mut namespaces: Vec<(Option<u8>, i8)> = Vec::new();
+        let mut namespace_has_antenna = false;
+        for (kind, data) in iterator.into_iter().map_while(Result::ok) {
+            match kind {
+                Kind::AntennaSignal => {
+                    if let Ok(antenna_signal) = field::from_bytes::<AntennaSignal>(data) {
+                        namespaces.push((None, antenna_signal.value));
+                        namespace_has_antenna = false;
+                    }
+                }
+                Kind::AntennaNoise => {
+                    if let Ok(noise) = field::from_bytes::<AntennaNoise>(data) {
+                        signal.noise = signal.noise.or(Some(noise.value));
+                    }
+                }
+                // the antenna index follows the signal of its namespace
+                Kind::Antenna if !namespace_has_antenna => {
+                    if let (Some(last), Ok(antenna)) =
+                        (namespaces.last_mut(), field::from_bytes::<Antenna>(data))
+                    {
+                        last.0 = Some(antenna.value);
+                        namespace_has_antenna = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(&(_, combined)) = namespaces.first() {
+            signal.combined = Some(combined);
+        }
+        if namespaces.len() > 1 {
+            signal.chains = namespaces[1..]
+                .iter()
+                .map(|&(antenna, signal)| ChainSignal { antenna, signal })
+                .collect();
+        }
+        signal
+    }
+
+    /// The RSSI according to `rule`, the combined signal when there are no chains.
+    pub fn rssi(&self, rule: RssiCombine) -> Option<i8> {
+        if self.chains.is_empty() {
+            return self.combined;
+        }
+
+        match rule {
+            RssiCombine::Combined => self.combined,
+            RssiCombine::Max => self.chains.iter().map(|chain| chain.signal).max(),
+            RssiCombine::Mean => {
+                // average in mW, not in dBm
+                let mean_mw = self
+                    .chains
+                    .iter()
+                    .map(|chain| 10f64.powf(chain.signal as f64 / 10.0))
+                    .sum::<f64>()
+                    / self.chains.len() as f64;
+                Some((10.0 * mean_mw.log10()).round() as i8)
+            }
+            RssiCombine::Primary => self
+                .chains
+                .iter()
+                .find(|chain| chain.antenna.unwrap_or(0) == 0)
+                .or(self.chains.first())
+                .map(|chain| chain.signal),
+        }
+    }
+}
+
+/// Average noise floor of the observations reporting one.
+pub fn average_noise(observations: &[Observation]) -> Option<f64> {
+    average(observations.iter().filter_map(|o| o.noise).map(f64::from))
+}
+
+/// Average SNR of the observations reporting a noise floor.
+pub fn average_snr(observations: &[Observation]) -> Option<f64> {
+    average(observations.iter().filter_map(|o| o.snr()).map(f64::from))
+}
+
+/// Average signal of each receive chain, by antenna index (or position when the adapter doesn't
+/// report antennas).
+pub fn average_chains(observations: &[Observation]) -> Vec<(u8, f64)> {
+    let mut chains: BTreeMap<u8, Vec<f64>> = BTreeMap::new();
+    for observation in observations {
+        for (index, chain) in observation.chains.iter().enumerate() {
+            chains
+                .entry(chain.antenna.unwrap_or(index as u8))
+                .or_default()
+                .push(chain.signal as f64);
+        }
+    }
+    chains
+        .into_iter()
+        .filter_map(|(antenna, signals)| Some((antenna, average(signals.into_iter())?)))
+        .collect()
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+#[cfg(test)]
+mod tests_signal {
+    use super::*;
+
+    #[test]
+    fn test_from_radiotap() {
+        // present words: flags + signal + noise + ext + radiotap ns, then signal + antenna twice
+        let radiotap = [
+            0x00, 0x00, 0x17, 0x00, // version, pad, length 23
+            0x62, 0x00, 0x00, 0xa0, // flags, signal, noise, radiotap ns, ext
+            0x20, 0x08, 0x00, 0xa0, // signal, antenna, radiotap ns, ext
+            0x20, 0x08, 0x00, 0x00, // signal, antenna
+            0x00, 0xd6, 0xa0, // flags, -42 dBm, -96 dBm noise
+            0xd4, 0x00, 0xd2, 0x01, // chain 0 at -44 dBm, chain 1 at -46 dBm
+        ];
+        let signal = RadiotapSignal::from_radiotap(&radiotap);
+        assert_eq!(signal.combined, Some(-42));
+        assert_eq!(signal.noise, Some(-96));
+        assert_eq!(
+            signal.chains,
+            vec![
+                ChainSignal {
+                    antenna: Some(0),
+                    signal: -44
+                },
+                ChainSignal {
+                    antenna: Some(1),
+                    signal: -46
+                }
+            ]
+        );
+
+        assert_eq!(signal.rssi(RssiCombine::Combined), Some(-42));
+        assert_eq!(signal.rssi(RssiCombine::Max), Some(-44));
+        assert_eq!(signal.rssi(RssiCombine::Mean), Some(-45));
+        assert_eq!(signal.rssi(RssiCombine::Primary), Some(-44));
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+const HEX_PREFIX: &str = "$HEX[";
+
+/// Raw SSID bytes. An SSID is up to 32 arbitrary bytes, often UTF-8 but also Latin-1, GBK...
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ssid(Vec<u8>);
+
+impl Ssid {
+    pub fn new(bytes: &[u8]) -> Self {
+        Ssid(bytes.to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Parses an SSID as printed by hashcat, either plain or `$HEX[...]` encoded.
+    pub fn from_hashcat(s: &str) -> Self {
+        s.strip_prefix(HEX_PREFIX)
+            .and_then(|hex| hex.strip_suffix(']'))
+            .and_then(decode_hex)
+            .map(Ssid)
+            .unwrap_or_else(|| Ssid::new(s.as_bytes()))
+    }
+}
+
+/// Decodes a string of hex digit pairs.
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl fmt::Display for Ssid {
+    /// Prints the SSID as text when it is printable UTF-8, else as `$HEX[...]` like hashcat.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match std::str::from_utf8(&self.0) {
+            Ok(text) if !text.chars().any(char::is_control) && !text.starts_with(HEX_PREFIX) => {
+                write!(f, "{}", text)
+            }
+            _ => {
+                write!(f, "{}", HEX_PREFIX)?;
+                for byte in &self.0 {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_ssid {
+    use super::*;
+
+    #[test]
+    fn test_ssid_display() {
+        assert_eq!(Ssid::new(b"HomeNet").to_string(), "HomeNet");
+        assert_eq!(Ssid::new("Café".as_bytes()).to_string(), "Café");
+        // Latin-1 "Café"
+        assert_eq!(Ssid::new(b"Caf\xe9").to_string(), "$HEX[436166e9]");
+        assert_eq!(Ssid::new(b"a\tb").to_string(), "$HEX[610962]");
+        assert_eq!(Ssid::new(b"$HEX[41]").to_string(), "$HEX[244845585b34315d]");
+    }
+
+    #[test]
+    fn test_ssid_from_hashcat() {
+        assert_eq!(Ssid::from_hashcat("HomeNet"), Ssid::new(b"HomeNet"));
+        assert_eq!(Ssid::from_hashcat("$HEX[436166e9]"), Ssid::new(b"Caf\xe9"));
+        assert_eq!(Ssid::from_hashcat("$HEX[4g]"), Ssid::new(b"$HEX[4g]"));
+    }
+}
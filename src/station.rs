@@ -0,0 +1,201 @@
+use crate::AccessPoint;
+use crate::geo::{Observation, Position, get_position_at, haversine_distance, rssi_to_distance};
+use crate::packets::Capture;
+use crate::ssid::Ssid;
+use log::debug;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+/// A directed probe request, naming a network the station has joined before.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub ssid: Ssid,
+    pub timestamp: Duration,
+    /// where the probe was heard, if the GPS track covers it
+    pub position: Option<Position>,
+}
+
+/// A client station, seen in data frames exchanged with access points or in probe requests.
+#[derive(Debug, Clone)]
+pub struct Station {
+    pub mac: [u8; 6],
+    pub vendor: Option<String>,
+    /// locally administered address, as used by MAC randomization
+    pub randomized: bool,
+    pub first_seen: Duration,
+    pub last_seen: Duration,
+    /// positions where frames sent by the station were heard
+    pub observations: Vec<Observation>,
+    /// BSSIDs the station exchanged data frames with
+    pub bssids: BTreeSet<[u8; 6]>,
+    /// directed probe requests, in capture order
+    pub probes: Vec<Probe>,
+}
+
+impl Station {
+    /// The preferred network list: every probed SSID with its probes, sorted by SSID.
+    pub fn preferred_networks(&self) -> Vec<(&Ssid, Vec<&Probe>)> {
+        let mut networks: Vec<(&Ssid, Vec<&Probe>)> = Vec::new();
+        for probe in &self.probes {
+            match networks.iter_mut().find(|(ssid, _)| **ssid == probe.ssid) {
+                Some((_, probes)) => probes.push(probe),
+                None => networks.push((&probe.ssid, vec![probe])),
+            }
+        }
+        networks.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        networks
+    }
+
+    /// Observation with the strongest signal, where the station was closest to us.
+    pub fn strongest_observation(&self) -> Option<&Observation> {
+        self.observations.iter().max_by_key(|o| o.signal_strength)
+    }
+}
+
+/// Returns true for locally administered addresses, which phones use for MAC randomization.
+pub fn is_randomized_mac(mac: &[u8; 6]) -> bool {
+    mac[0] & 0x02 != 0
+}
+
+pub fn group_packets_by_station(
+    captures: &[Capture],
+    positions: &[Position],
+    max_gap: f64,
+) -> Vec<Station> {
+    let mut stations: HashMap<[u8; 6], Station> = HashMap::new();
+
+    for packet in captures.iter().flat_map(|c| c.packets.iter()) {
+        let Some(mac) = packet.station else {
+            continue;
+        };
+
+        let station = stations.entry(mac).or_insert_with(|| Station {
+            mac,
+            vendor: None,
+            randomized: is_randomized_mac(&mac),
+            first_seen: packet.timestamp,
+            last_seen: packet.timestamp,
+            observations: Vec::new(),
+            bssids: BTreeSet::new(),
+            probes: Vec::new(),
+        });
+
+        station.first_seen = station.first_seen.min(packet.timestamp);
+        station.last_seen = station.last_seen.max(packet.timestamp);
+        if let Some(bssid) = packet.bssid {
+            station.bssids.insert(bssid);
+        }
+
+        let position = get_position_at(&packet.timestamp, positions, max_gap).ok();
+        if packet.probe_request
+            && let Some(ref ssid) = packet.ssid
+        {
+            station.probes.push(Probe {
+                ssid: ssid.clone(),
+                timestamp: packet.timestamp,
+                position: position.clone(),
+            });
+        }
+
+        if packet.station_transmitted
+            && let Some(signal) = packet.signal_strength
+            && let Some(position) = position
+        {
+            station.observations.push(Observation {
+                position,
+                signal_strength: signal,
+                distance: rssi_to_distance(signal),
+                interface: packet.interface.clone(),
+                chains: packet.chain_signals.clone(),
+                noise: packet.noise,
+                off_channel: false,
+            });
+        }
+    }
+
+    let mut stations: Vec<Station> = stations.into_values().collect();
+    stations.sort_by_key(|station| station.mac);
+    stations
+}
+
+/// Records on each access point the stations that exchanged data with it.
+pub fn bind_clients_to_aps(aps: &mut [AccessPoint], stations: &[Station]) {
+    let mut clients: HashMap<[u8; 6], BTreeSet<[u8; 6]>> = HashMap::new();
+    for station in stations {
+        for bssid in &station.bssids {
+            clients.entry(*bssid).or_default().insert(station.mac);
+        }
+    }
+
+    for ap in aps.iter_mut() {
+        if let Some(ap_clients) = clients.remove(&ap.mac) {
+            debug!("AP {:02x?} has {} clients", ap.mac, ap_clients.len());
+            ap.clients = ap_clients;
+        }
+    }
+}
+
+/// Located access points broadcasting `ssid`, nearest to `position` first, with their distance in
+/// meters (0 when the position is unknown).
+pub fn locate_probed_network<'a>(
+    ssid: &Ssid,
+    position: Option<&Position>,
+    aps: &'a [AccessPoint],
+) -> Vec<(&'a AccessPoint, f64)> {
+    let mut located: Vec<(&AccessPoint, f64)> = aps
+        .iter()
+        .filter(|ap| ap.ssid.as_ref() == Some(ssid))
+        .filter_map(|ap| {
+            let ap_position = ap.estimated_position.as_ref()?;
+            let distance = position.map_or(0.0, |p| {
+                haversine_distance(
+                    p.latitude,
+                    p.longitude,
+                    ap_position.latitude,
+                    ap_position.longitude,
+                )
+            });
+            Some((ap, distance))
+        })
+        .collect();
+    located.sort_by(|a, b| a.1.total_cmp(&b.1));
+    located
+}
+
+#[cfg(test)]
+mod tests_station {
+    use super::*;
+
+    #[test]
+    fn test_is_randomized_mac() {
+        assert!(is_randomized_mac(&[0xda, 0xa1, 0x19, 0x00, 0x00, 0x01]));
+        assert!(is_randomized_mac(&[0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0xee]));
+        assert!(!is_randomized_mac(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]));
+        assert!(!is_randomized_mac(&[0xf0, 0x18, 0x98, 0x00, 0x00, 0x01]));
+    }
+
+    #[test]
+    fn test_preferred_networks() {
+        let probe = |ssid: &[u8], secs| Probe {
+            ssid: Ssid::new(ssid),
+            timestamp: Duration::from_secs(secs),
+            position: None,
+        };
+        let station = Station {
+            mac: [0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0xee],
+            vendor: None,
+            randomized: true,
+            first_seen: Duration::from_secs(1),
+            last_seen: Duration::from_secs(3),
+            observations: Vec::new(),
+            bssids: BTreeSet::new(),
+            probes: vec![probe(b"Work", 1), probe(b"Home", 2), probe(b"Work", 3)],
+        };
+
+        let networks = station.preferred_networks();
+        assert_eq!(networks.len(), 2);
+        assert_eq!(*networks[0].0, Ssid::new(b"Home"));
+        assert_eq!(*networks[1].0, Ssid::new(b"Work"));
+        assert_eq!(networks[1].1.len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+
+/// Wi-Fi Protected Setup vendor element: OUI 00:50:f2, type 4
+const WPS_OUI_TYPE: [u8; 4] = [0x00, 0x50, 0xf2, 0x04];
+
+const ATTR_DEVICE_NAME: u16 = 0x1011;
+const ATTR_MANUFACTURER: u16 = 0x1021;
+const ATTR_MODEL_NAME: u16 = 0x1023;
+const ATTR_MODEL_NUMBER: u16 = 0x1024;
+const ATTR_SERIAL_NUMBER: u16 = 0x1042;
+const ATTR_WPS_STATE: u16 = 0x1044;
+const ATTR_UUID_E: u16 = 0x1047;
+const ATTR_AP_SETUP_LOCKED: u16 = 0x1057;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WpsState {
+    /// the AP still runs its factory settings
+    Unconfigured,
+    Configured,
+}
+
+impl fmt::Display for WpsState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WpsState::Unconfigured => write!(f, "unconfigured"),
+            WpsState::Configured => write!(f, "configured"),
+        }
+    }
+}
+
+/// Device attributes advertised in the WPS element of beacons and probe responses. Beacons
+/// usually only carry the state, probe responses the full device description.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WpsInfo {
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub device_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub state: Option<WpsState>,
+    /// the AP stopped accepting PINs, usually after too many failed attempts
+    pub ap_setup_locked: Option<bool>,
+    pub uuid: Option<[u8; 16]>,
+}
+
+impl WpsInfo {
+    /// Fills the attributes missing here with those of `other`.
+    pub fn merge(&mut self, other: &WpsInfo) {
+        let fill = |field: &mut Option<String>, value: &Option<String>| {
+            if field.is_none() {
+                *field = value.clone();
+            }
+        };
+        fill(&mut self.manufacturer, &other.manufacturer);
+        fill(&mut self.model_name, &other.model_name);
+        fill(&mut self.model_number, &other.model_number);
+        fill(&mut self.device_name, &other.device_name);
+        fill(&mut self.serial_number, &other.serial_number);
+        self.state = self.state.or(other.state);
+        self.ap_setup_locked = self.ap_setup_locked.or(other.ap_setup_locked);
+        self.uuid = self.uuid.or(other.uuid);
+    }
+
+    /// Model name and number, e.g. "Archer C6 2.0".
+    pub fn model(&self) -> Option<String> {
+        match (&self.model_name, &self.model_number) {
+            (Some(name), Some(number)) if name != number => Some(format!("{} {}", name, number)),
+            (Some(name), _) => Some(name.clone()),
+            (None, number) => number.clone(),
+        }
+    }
+
+    pub fn uuid_string(&self) -> Option<String> {
+        let uuid = self.uuid?;
+        let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+        Some(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        ))
+    }
+}
+
+/// Returns the WPS attributes of a management frame body, whose fixed parameters are `fixed_len`
+/// bytes long. The attributes may be split over several consecutive WPS elements.
+pub fn parse_wps_element(frame_body: &[u8], fixed_len: usize) -> Option<WpsInfo> {
+    let mut attributes: Option<Vec<u8>> = None;
+    let mut offset = fixed_len; // skip fixed parameters
+
+    while offset + 2 <= frame_body.len() {
+        let tag_number = frame_body[offset];
+        let tag_length = frame_body[offset + 1] as usize;
+
+        if offset + 2 + tag_length > frame_body.len() {
+            break;
+        }
+        let tag_data = &frame_body[offset + 2..offset + 2 + tag_length];
+
+        // vendor specific - WPS
+        if tag_number == 221 && tag_data.starts_with(&WPS_OUI_TYPE) {
+            attributes
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(&tag_data[4..]);
+        }
+
+        offset += 2 + tag_length;
+    }
+
+    Some(parse_wps_attributes(&attributes?))
+}
+
+/// Attributes are big endian type (2 bytes), length (2 bytes), value.
+fn parse_wps_attributes(data: &[u8]) -> WpsInfo {
+    let mut info = WpsInfo::default();
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() {
+        let attribute = u16::from_be_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+
+        let Some(value) = data.get(offset + 4..offset + 4 + length) else {
+            break;
+        };
+
+        match attribute {
+            ATTR_MANUFACTURER => info.manufacturer = parse_text(value),
+            ATTR_MODEL_NAME => info.model_name = parse_text(value),
+            ATTR_MODEL_NUMBER => info.model_number = parse_text(value),
+            ATTR_DEVICE_NAME => info.device_name = parse_text(value),
+            ATTR_SERIAL_NUMBER => info.serial_number = parse_text(value),
+            ATTR_WPS_STATE => {
+                info.state = match value.first() {
+                    Some(1) => Some(WpsState::Unconfigured),
+                    Some(2) => Some(WpsState::Configured),
+                    _ => None,
+                }
+            }
+            ATTR_AP_SETUP_LOCKED => info.ap_setup_locked = value.first().map(|&b| b != 0),
+            ATTR_UUID_E => info.uuid = value.try_into().ok(),
+            _ => {}
+        }
+
+        offset += 4 + length;
+    }
+
+    info
+}
+
+/// Text attributes are often NUL padded, and blank on cheap devices.
+fn parse_text(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests_wps {
+    use super::*;
+
+    fn attribute(attribute: u16, value: &[u8]) -> Vec<u8> {
+        let mut data = attribute.to_be_bytes().to_vec();
+        data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        data.extend_from_slice(value);
+        data
+    }
+
+    #[test]
+    fn test_parse_wps_element() {
+        let mut attributes = attribute(ATTR_WPS_STATE, &[2]);
+        attributes.extend(attribute(ATTR_AP_SETUP_LOCKED, &[1]));
+        attributes.extend(attribute(ATTR_MANUFACTURER, b"TP-Link\0"));
+        attributes.extend(attribute(ATTR_MODEL_NAME, b"Archer C6"));
+        attributes.extend(attribute(ATTR_MODEL_NUMBER, b"2.0"));
+        attributes.extend(attribute(ATTR_SERIAL_NUMBER, b"  "));
+        attributes.extend(attribute(ATTR_UUID_E, &[0x11; 16]));
+
+        // SSID element, then the WPS attributes split over two elements
+        let mut body = vec![0, 4];
+        body.extend_from_slice(b"Home");
+        for chunk in attributes.chunks(30) {
+            body.extend_from_slice(&[221, chunk.len() as u8 + 4]);
+            body.extend_from_slice(&WPS_OUI_TYPE);
+            body.extend_from_slice(chunk);
+        }
+
+        let wps = parse_wps_element(&body, 0).unwrap();
+        assert_eq!(wps.state, Some(WpsState::Configured));
+        assert_eq!(wps.ap_setup_locked, Some(true));
+        assert_eq!(wps.manufacturer.as_deref(), Some("TP-Link"));
+        assert_eq!(wps.model().as_deref(), Some("Archer C6 2.0"));
+        assert_eq!(wps.serial_number, None);
+        assert_eq!(
+            wps.uuid_string().as_deref(),
+            Some("11111111-1111-1111-1111-111111111111")
+        );
+
+        assert_eq!(parse_wps_element(&body[..6], 0), None);
+    }
+}